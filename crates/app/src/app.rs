@@ -10,11 +10,30 @@ pub struct Args {
     /// Path to gltf/glb file to be rendered
     #[arg(short, long, default_value = "assets/Avocado.glb")]
     gltf: String,
+
+    /// Width and height of the pixelated output
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u32).range(1..))]
+    pixel_size: u32,
+
+    /// Palette file (.gpl, .hex, .pal or .png swatch) to quantize the pixelated output to
+    #[arg(long)]
+    palette: Option<String>,
+
+    /// Image whose middle row gives the band colors for ramp cel shading.  Selects the ramp
+    /// shading model
+    #[arg(long)]
+    cel_ramp: Option<String>,
+
+    /// Render without opening a window
+    #[command(subcommand)]
+    pub command: Option<crate::batch::Command>,
 }
 
 struct RenderViewport {
+    wgpu_render_state: egui_wgpu::RenderState,
     renderer: render::Renderer,
     render_texture: egui::load::SizedTexture,
+    pixel_texture: egui::load::SizedTexture,
 }
 
 impl RenderViewport {
@@ -36,12 +55,57 @@ impl RenderViewport {
             size: egui::Vec2::new(App::VIEWPORT_WIDTH as f32, App::VIEWPORT_HEIGHT as f32),
             id: render_texture_id,
         };
+
+        let pixel_texture_id = wgpu_render_state.renderer.write().register_native_texture(
+            &wgpu_render_state.device,
+            renderer.get_pixel_texture_view(),
+            wgpu::FilterMode::Nearest,
+        );
+
+        let pixel_texture = egui::load::SizedTexture {
+            size: egui::Vec2::new(App::VIEWPORT_WIDTH as f32, App::VIEWPORT_HEIGHT as f32),
+            id: pixel_texture_id,
+        };
+
         Self {
+            wgpu_render_state: wgpu_render_state.clone(),
             render_texture,
+            pixel_texture,
             renderer,
         }
     }
 
+    fn set_pixel_size(&mut self, size: u32) {
+        self.renderer.set_pixel_size(&[size, size]);
+
+        // The pixelizer recreates its output texture on resize
+        self.wgpu_render_state
+            .renderer
+            .write()
+            .update_egui_texture_from_wgpu_texture(
+                &self.wgpu_render_state.device,
+                self.renderer.get_pixel_texture_view(),
+                wgpu::FilterMode::Nearest,
+                self.pixel_texture.id,
+            );
+    }
+
+    fn draw_pixelized(&mut self, ui: &mut egui::Ui) {
+        let image =
+            egui::Image::from_texture(self.pixel_texture).max_size(egui::Vec2::new(512.0, 512.0));
+        let size = image.calc_size(ui.available_size(), image.size());
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+
+        // A snapped camera moves in whole pixels, shift the image by the sub-pixel remainder
+        let [dx, dy] = self.renderer.snap_offset();
+        let [w, h] = self.renderer.pixel_size();
+        let offset = egui::Vec2::new(dx * rect.width() / w as f32, dy * rect.height() / h as f32);
+        ui.scope(|ui| {
+            ui.set_clip_rect(rect);
+            image.paint_at(ui, rect.translate(offset));
+        });
+    }
+
     fn draw(&mut self, ui: &mut egui::Ui) {
         self.renderer.render();
         let image = egui::Image::from_texture(self.render_texture)
@@ -76,8 +140,38 @@ impl RenderViewport {
 }
 
 pub struct App {
+    // Animation playback.  The clip is sampled at `num_frames` evenly spaced times, frames are
+    // numbered from 1 as shown on the timeline.
+    animation: Option<usize>,
     num_frames: i32,
     cur_frame: i32,
+    playing: bool,
+    looping: bool,
+    playback_fps: u32,
+    /// Time the current frame has been shown while playing, in seconds
+    frame_elapsed: f32,
+
+    pixel_size: u32,
+    palette: Option<palette::Palette>,
+    use_palette: bool,
+
+    // Palette generation settings.  `locked_colors` parallels `palette.colors`
+    palette_method: palette::extract::Method,
+    palette_count: usize,
+    locked_colors: Vec<bool>,
+    palette_export_path: String,
+    palette_status: String,
+
+    // Sprite sheet export settings
+    sheet_options: sprite::SheetOptions,
+    sheet_format: sprite::Format,
+    sheet_export_path: String,
+    sheet_status: String,
+    /// Facings exported per frame, 1 exports the current view only
+    sheet_directions: u32,
+    sheet_start_angle: f32,
+    /// Companion maps exported next to the sheet, in `SpriteMap::ALL` order
+    sheet_maps: Vec<render::SpriteMap>,
 
     render_viewport: RenderViewport,
 }
@@ -85,16 +179,57 @@ pub struct App {
 impl App {
     const VIEWPORT_WIDTH: u32 = 512;
     const VIEWPORT_HEIGHT: u32 = 512;
+    const PIXEL_SIZES: [u32; 8] = [16, 32, 48, 64, 96, 128, 192, 256];
+    const DIRECTION_COUNTS: [u32; 4] = [1, 4, 8, 16];
 
     /// Called once before the first frame.
     pub fn new(args: Args, cc: &eframe::CreationContext<'_>) -> Self {
         //let model = model::Model::from_gltf("assets/Fox.glb");
-        let model = model::Model::from_gltf(&args.gltf);
+        let model = model::Model::from_gltf(&args.gltf)
+            .unwrap_or_else(|e| panic!("Failed to load '{}': {}", args.gltf, e));
+
+        let mut render_viewport =
+            RenderViewport::new(cc.wgpu_render_state.as_ref().unwrap(), &model);
+        render_viewport.set_pixel_size(args.pixel_size);
+
+        let palette = args.palette.as_ref().map(|path| {
+            palette::Palette::load(path)
+                .unwrap_or_else(|e| panic!("Failed to load palette '{}': {}", path, e))
+        });
+        render_viewport.renderer.set_palette(palette.as_ref());
+
+        if let Some(path) = &args.cel_ramp {
+            let ramp = image::open(path)
+                .unwrap_or_else(|e| panic!("Failed to load cel ramp '{}': {}", path, e));
+            let renderer = &mut render_viewport.renderer;
+            renderer.set_cel_ramp(&ramp.to_rgba8());
+            renderer.set_shading(render::ShadingModel::CelRamp);
+        }
 
         App {
-            num_frames: 60, // TODO: connect this value
-            cur_frame: 0,
-            render_viewport: RenderViewport::new(cc.wgpu_render_state.as_ref().unwrap(), &model),
+            animation: (!model.animations.is_empty()).then_some(0),
+            num_frames: 8,
+            cur_frame: 1,
+            playing: false,
+            looping: true,
+            playback_fps: 12,
+            frame_elapsed: 0.0,
+            pixel_size: render_viewport.renderer.pixel_size()[0],
+            use_palette: palette.is_some(),
+            locked_colors: vec![false; palette.as_ref().map_or(0, |p| p.len())],
+            palette,
+            palette_method: palette::extract::Method::default(),
+            palette_count: 16,
+            palette_export_path: "palette.gpl".to_string(),
+            palette_status: String::new(),
+            sheet_options: sprite::SheetOptions::default(),
+            sheet_format: sprite::Format::default(),
+            sheet_export_path: "sheet.png".to_string(),
+            sheet_status: String::new(),
+            sheet_directions: 1,
+            sheet_start_angle: render::Turntable::DEFAULT_START_ANGLE,
+            sheet_maps: Vec::new(),
+            render_viewport,
         }
     }
 
@@ -103,9 +238,559 @@ impl App {
     }
 
     fn render_right_viewport(&mut self, ui: &mut egui::Ui) {
-        let image = egui::Image::new(egui::include_image!("../../../assets/monkey_pixel.png"))
-            .max_size(egui::Vec2::new(512.0, 512.0));
-        ui.add(image);
+        self.render_viewport.draw_pixelized(ui);
+    }
+
+    fn render_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let prev_pixel_size = self.pixel_size;
+            egui::ComboBox::from_label("Pixel size")
+                .selected_text(format!("{0}x{0}", self.pixel_size))
+                .show_ui(ui, |ui| {
+                    for size in App::PIXEL_SIZES {
+                        ui.selectable_value(&mut self.pixel_size, size, format!("{0}x{0}", size));
+                    }
+                });
+            if self.pixel_size != prev_pixel_size {
+                self.render_viewport.set_pixel_size(self.pixel_size);
+            }
+
+            let renderer = &mut self.render_viewport.renderer;
+            let mut filter = renderer.pixel_filter();
+            egui::ComboBox::from_label("Filter")
+                .selected_text(filter.name())
+                .show_ui(ui, |ui| {
+                    for f in render::PixelFilter::ALL {
+                        ui.selectable_value(&mut filter, f, f.name());
+                    }
+                });
+            renderer.set_pixel_filter(filter);
+
+            let mut alpha_threshold = renderer.alpha_threshold();
+            let slider = egui::Slider::new(&mut alpha_threshold, 0.0..=1.0).text("Alpha cutoff");
+            if ui.add(slider).changed() {
+                renderer.set_alpha_threshold(alpha_threshold);
+            }
+
+            let mut shading = renderer.shading();
+            egui::ComboBox::from_label("Shading")
+                .selected_text(shading.name())
+                .show_ui(ui, |ui| {
+                    for s in render::ShadingModel::ALL {
+                        ui.selectable_value(&mut shading, s, s.name());
+                    }
+                });
+            renderer.set_shading(shading);
+
+            let mut cull_mode = renderer.cull_mode();
+            egui::ComboBox::from_label("Culling")
+                .selected_text(cull_mode.name())
+                .show_ui(ui, |ui| {
+                    for c in render::CullMode::ALL {
+                        ui.selectable_value(&mut cull_mode, c, c.name());
+                    }
+                });
+            renderer.set_cull_mode(cull_mode);
+
+            if shading.is_cel() {
+                let mut bands = renderer.cel_shading().bands;
+                let slider =
+                    egui::Slider::new(&mut bands, 2..=render::CelShading::MAX_BANDS).text("Bands");
+                if ui.add(slider).changed() {
+                    // Keep the specular settings, respace the thresholds
+                    let even = render::CelShading::with_bands(bands);
+                    renderer.set_cel_shading(render::CelShading {
+                        bands: even.bands,
+                        thresholds: even.thresholds,
+                        ..renderer.cel_shading().clone()
+                    });
+                }
+            }
+        });
+        self.render_camera_controls(ui);
+        self.render_outline_controls(ui);
+        self.render_palette_controls(ui);
+        self.render_sheet_controls(ui);
+    }
+
+    fn render_camera_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let renderer = &mut self.render_viewport.renderer;
+            let projection = renderer.projection();
+
+            let mut orthographic = projection.is_orthographic();
+            egui::ComboBox::from_label("Projection")
+                .selected_text(if orthographic {
+                    "Orthographic"
+                } else {
+                    "Perspective"
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut orthographic, false, "Perspective");
+                    ui.selectable_value(&mut orthographic, true, "Orthographic");
+                });
+            if orthographic != projection.is_orthographic() {
+                renderer.set_projection(if orthographic {
+                    render::Projection::Orthographic {
+                        units_per_pixel: renderer.fit_units_per_pixel(),
+                    }
+                } else {
+                    render::Projection::default()
+                });
+            }
+
+            if let render::Projection::Orthographic {
+                mut units_per_pixel,
+            } = renderer.projection()
+            {
+                let speed = units_per_pixel * 0.01;
+                let drag = egui::DragValue::new(&mut units_per_pixel)
+                    .speed(speed)
+                    .range(1e-6..=f32::MAX)
+                    .max_decimals(6);
+                if ui.add(drag).changed() {
+                    renderer.set_projection(render::Projection::Orthographic { units_per_pixel });
+                }
+                ui.label("Units/pixel");
+            }
+
+            let mut snap = renderer.pixel_snap();
+            if ui.checkbox(&mut snap, "Pixel snap").changed() {
+                renderer.set_pixel_snap(snap);
+            }
+            let mut snap_root = renderer.snap_root_motion();
+            if ui.checkbox(&mut snap_root, "Snap root motion").changed() {
+                renderer.set_snap_root_motion(snap_root);
+            }
+
+            ui.separator();
+            for preset in render::CameraPreset::ALL {
+                if ui.button(preset.name()).clicked() {
+                    renderer.set_camera_preset(preset);
+                }
+            }
+        });
+    }
+
+    fn render_outline_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let renderer = &mut self.render_viewport.renderer;
+            let mut outline = *renderer.outline();
+
+            egui::ComboBox::from_label("Outline")
+                .selected_text(outline.technique.name())
+                .show_ui(ui, |ui| {
+                    for t in render::OutlineTechnique::ALL {
+                        ui.selectable_value(&mut outline.technique, t, t.name());
+                    }
+                });
+
+            if outline.technique != render::OutlineTechnique::None {
+                egui::ComboBox::from_label("Edges")
+                    .selected_text(outline.edges.name())
+                    .show_ui(ui, |ui| {
+                        for e in render::OutlineEdges::ALL {
+                            ui.selectable_value(&mut outline.edges, e, e.name());
+                        }
+                    });
+                ui.add(
+                    egui::Slider::new(&mut outline.thickness, 1..=render::Outline::MAX_THICKNESS)
+                        .text("Thickness"),
+                );
+
+                // Line color mode, keeping the value of the current mode when reselected
+                let modes = [
+                    ("Fixed", render::LineColor::Fixed([0, 0, 0])),
+                    ("Palette index", render::LineColor::PaletteIndex(0)),
+                    ("Darken", render::LineColor::Darken(0.5)),
+                ];
+                let mode_name = |c: &render::LineColor| match c {
+                    render::LineColor::Fixed(_) => "Fixed",
+                    render::LineColor::PaletteIndex(_) => "Palette index",
+                    render::LineColor::Darken(_) => "Darken",
+                };
+                egui::ComboBox::from_label("Line color")
+                    .selected_text(mode_name(&outline.color))
+                    .show_ui(ui, |ui| {
+                        for (name, default) in modes {
+                            let selected = mode_name(&outline.color) == name;
+                            if ui.selectable_label(selected, name).clicked() && !selected {
+                                outline.color = default;
+                            }
+                        }
+                    });
+                match &mut outline.color {
+                    render::LineColor::Fixed(c) => {
+                        ui.color_edit_button_srgb(c);
+                    }
+                    render::LineColor::PaletteIndex(idx) => {
+                        let max = self
+                            .palette
+                            .as_ref()
+                            .map_or(0, |p| p.len().saturating_sub(1));
+                        ui.add(egui::DragValue::new(idx).range(0..=max));
+                    }
+                    render::LineColor::Darken(f) => {
+                        ui.add(egui::Slider::new(f, 0.05..=1.0).text("Factor"));
+                    }
+                }
+            }
+
+            renderer.set_outline(outline);
+        });
+    }
+
+    fn render_palette_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let renderer = &mut self.render_viewport.renderer;
+            match &self.palette {
+                Some(palette) => {
+                    let label = format!("Palette: {} ({} colors)", palette.name, palette.len());
+                    if ui.checkbox(&mut self.use_palette, label).changed() {
+                        renderer.set_palette(self.use_palette.then_some(palette));
+                    }
+
+                    // Swatches, click to lock an entry during generation
+                    for (color, locked) in palette.colors.iter().zip(self.locked_colors.iter_mut())
+                    {
+                        let (rect, response) =
+                            ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::click());
+                        let [r, g, b] = *color;
+                        ui.painter()
+                            .rect_filled(rect, 0.0, egui::Color32::from_rgb(r, g, b));
+                        if *locked {
+                            ui.painter().rect_stroke(
+                                rect,
+                                0.0,
+                                egui::Stroke::new(2.0, egui::Color32::WHITE),
+                                egui::StrokeKind::Inside,
+                            );
+                        }
+                        if response
+                            .on_hover_text(format!("#{:02x}{:02x}{:02x}, click to lock", r, g, b))
+                            .clicked()
+                        {
+                            *locked = !*locked;
+                        }
+                    }
+                }
+                None => {
+                    ui.label("Palette: none");
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let renderer = &mut self.render_viewport.renderer;
+            let (mut dither, mut strength) = renderer.dither();
+            egui::ComboBox::from_label("Dither")
+                .selected_text(dither.name())
+                .show_ui(ui, |ui| {
+                    for d in render::Dither::ALL {
+                        if !renderer.supports_dither(d) {
+                            continue;
+                        }
+                        ui.selectable_value(&mut dither, d, d.name());
+                    }
+                });
+            ui.add_enabled(
+                dither != render::Dither::None,
+                egui::Slider::new(&mut strength, 0.0..=1.0).text("Strength"),
+            );
+            renderer.set_dither(dither, strength);
+
+            ui.separator();
+            egui::ComboBox::from_label("Method")
+                .selected_text(self.palette_method.name())
+                .show_ui(ui, |ui| {
+                    for m in palette::extract::Method::ALL {
+                        ui.selectable_value(&mut self.palette_method, m, m.name());
+                    }
+                });
+            ui.add(
+                egui::DragValue::new(&mut self.palette_count)
+                    .range(2..=palette::Palette::MAX_COLORS)
+                    .suffix(" colors"),
+            );
+            if ui.button("Generate palette").clicked() {
+                self.generate_palette();
+            }
+
+            ui.separator();
+            ui.text_edit_singleline(&mut self.palette_export_path);
+            if ui
+                .add_enabled(self.palette.is_some(), egui::Button::new("Export"))
+                .on_hover_text("Write palette as .gpl, .hex or .pal")
+                .clicked()
+            {
+                if let Some(palette) = &self.palette {
+                    self.palette_status = match palette.save(&self.palette_export_path) {
+                        Ok(()) => format!("Wrote {}", self.palette_export_path),
+                        Err(e) => format!("Export failed: {}", e),
+                    };
+                }
+            }
+            ui.label(&self.palette_status);
+        });
+    }
+
+    fn render_sheet_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let options = &mut self.sheet_options;
+            egui::ComboBox::from_label("Packing")
+                .selected_text(options.packing.name())
+                .show_ui(ui, |ui| {
+                    for p in sprite::Packing::ALL {
+                        ui.selectable_value(&mut options.packing, p, p.name());
+                    }
+                });
+            ui.add(
+                egui::DragValue::new(&mut options.padding)
+                    .range(0..=16)
+                    .prefix("Padding: "),
+            );
+            ui.checkbox(&mut options.trim, "Trim");
+            egui::ComboBox::from_label("Format")
+                .selected_text(self.sheet_format.name())
+                .show_ui(ui, |ui| {
+                    for f in sprite::Format::ALL {
+                        ui.selectable_value(&mut self.sheet_format, f, f.name());
+                    }
+                });
+            egui::ComboBox::from_label("Directions")
+                .selected_text(self.sheet_directions.to_string())
+                .show_ui(ui, |ui| {
+                    for count in App::DIRECTION_COUNTS {
+                        ui.selectable_value(&mut self.sheet_directions, count, count.to_string());
+                    }
+                });
+            ui.add_enabled(
+                self.sheet_directions > 1,
+                egui::DragValue::new(&mut self.sheet_start_angle)
+                    .range(-360.0..=360.0)
+                    .suffix("°")
+                    .prefix("Start: "),
+            )
+            .on_hover_text("Model rotation of the N facing");
+
+            ui.separator();
+            ui.label("Maps:");
+            for map in render::SpriteMap::ALL {
+                let mut export = self.sheet_maps.contains(&map);
+                if ui.checkbox(&mut export, map.name()).changed() {
+                    let selected = std::mem::take(&mut self.sheet_maps);
+                    self.sheet_maps = render::SpriteMap::ALL
+                        .into_iter()
+                        .filter(|&m| {
+                            if m == map {
+                                export
+                            } else {
+                                selected.contains(&m)
+                            }
+                        })
+                        .collect();
+                }
+            }
+
+            ui.separator();
+            ui.text_edit_singleline(&mut self.sheet_export_path);
+            if ui
+                .button("Export sheet")
+                .on_hover_text("Write the sprite sheet PNG and its metadata in the selected format")
+                .clicked()
+            {
+                self.sheet_status = match self.export_sheet() {
+                    Ok(()) => format!("Wrote {}", self.sheet_export_path),
+                    Err(e) => format!("Export failed: {}", e),
+                };
+            }
+            ui.label(&self.sheet_status);
+        });
+    }
+
+    /// Render the sprite frames and pack them into a sheet at `sheet_export_path`.  With an
+    /// animation clip selected every timeline frame is exported, with several directions every
+    /// frame is rendered once per turntable facing.  Selected companion maps are packed into
+    /// sheets of the same layout next to it.
+    fn export_sheet(&mut self) -> Result<(), sprite::Error> {
+        let frame_duration = self.frame_duration();
+        let turntable = (self.sheet_directions > 1)
+            .then(|| render::Turntable::new(self.sheet_directions, self.sheet_start_angle));
+        let maps = &self.sheet_maps;
+        let renderer = &mut self.render_viewport.renderer;
+
+        // Each facing of the current pose
+        let render_facings = |renderer: &mut render::Renderer| match &turntable {
+            Some(turntable) => renderer.render_turntable(turntable, maps),
+            None => vec![render::TurntableFrame {
+                direction: renderer.model_yaw().to_string(),
+                yaw: renderer.model_yaw(),
+                image: renderer.render_to_image(),
+                maps: maps
+                    .iter()
+                    .map(|&map| renderer.read_map_pixels(map))
+                    .collect(),
+            }],
+        };
+
+        // (animation name, frame index, frame duration) of each pose
+        let poses: Vec<(String, u32, u32)> = match self.animation {
+            Some(animation) => {
+                let name = &renderer.animations()[animation].name;
+                let duration_ms = (frame_duration * 1000.0).round() as u32;
+                (0..self.num_frames as u32)
+                    .map(|index| (name.clone(), index, duration_ms))
+                    .collect()
+            }
+            None => vec![(String::new(), 0, 100)],
+        };
+
+        let mut rendered = Vec::with_capacity(poses.len());
+        for &(_, index, _) in &poses {
+            if let Some(animation) = self.animation {
+                renderer.set_animation_time(animation, index as f32 * frame_duration);
+            }
+            rendered.push(render_facings(renderer).into_iter());
+        }
+
+        // Direction-major, so each clip of the exported metadata is one run of frames
+        let facing_count = rendered.first().map_or(0, |facings| facings.len());
+        let mut frames = Vec::with_capacity(facing_count * poses.len());
+        let mut companions: Vec<Vec<image::RgbaImage>> = vec![Vec::new(); maps.len()];
+        for _ in 0..facing_count {
+            for ((animation_name, index, duration_ms), facings) in poses.iter().zip(&mut rendered) {
+                let facing = facings.next().unwrap();
+                for (companion, map_image) in companions.iter_mut().zip(facing.maps) {
+                    companion.push(map_image);
+                }
+                frames.push(sprite::Frame {
+                    image: facing.image,
+                    animation: animation_name.clone(),
+                    direction: facing.direction,
+                    index: *index,
+                    duration_ms: *duration_ms,
+                });
+            }
+        }
+
+        // The viewport pose is restored on the next update
+        let sheet = sprite::Sheet::pack(&frames, &self.sheet_options)?;
+        sheet.save(&self.sheet_export_path, &[self.sheet_format])?;
+        let path = std::path::Path::new(&self.sheet_export_path);
+        for (map, images) in maps.iter().zip(&companions) {
+            sheet
+                .companion(images)?
+                .save(sprite::Sheet::companion_path(path, map.name()))?;
+        }
+        Ok(())
+    }
+
+    /// Clip time between two sprite frames, in seconds
+    fn frame_duration(&self) -> f32 {
+        self.animation.map_or(0.0, |animation| {
+            self.render_viewport.renderer.animations()[animation].duration / self.num_frames as f32
+        })
+    }
+
+    /// Advance the current frame at `playback_fps`, wrapping or stopping at the last frame
+    fn advance_playback(&mut self, dt: f32) {
+        let period = 1.0 / self.playback_fps as f32;
+        self.frame_elapsed += dt;
+        while self.frame_elapsed >= period {
+            self.frame_elapsed -= period;
+            if self.cur_frame < self.num_frames {
+                self.cur_frame += 1;
+            } else if self.looping {
+                self.cur_frame = 1;
+            } else {
+                self.playing = false;
+                self.frame_elapsed = 0.0;
+                break;
+            }
+        }
+    }
+
+    /// Pose the model for the current clip and frame
+    fn apply_pose(&mut self) {
+        let time = (self.cur_frame - 1) as f32 * self.frame_duration();
+        let renderer = &mut self.render_viewport.renderer;
+        match self.animation {
+            Some(animation) => renderer.set_animation_time(animation, time),
+            None => renderer.reset_pose(),
+        }
+    }
+
+    fn render_timeline_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let animations = self.render_viewport.renderer.animations();
+            let clip_name =
+                |clip: Option<usize>| clip.map_or("Rest pose", |idx| animations[idx].name.as_str());
+            egui::ComboBox::from_label("Clip")
+                .selected_text(clip_name(self.animation))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.animation, None, clip_name(None));
+                    for idx in 0..animations.len() {
+                        ui.selectable_value(&mut self.animation, Some(idx), clip_name(Some(idx)));
+                    }
+                });
+
+            ui.add_enabled_ui(self.animation.is_some(), |ui| {
+                if ui.button("⏮").on_hover_text("Previous frame").clicked() {
+                    self.playing = false;
+                    self.cur_frame = if self.cur_frame > 1 {
+                        self.cur_frame - 1
+                    } else {
+                        self.num_frames
+                    };
+                }
+                let play_label = if self.playing { "⏸" } else { "▶" };
+                if ui.button(play_label).on_hover_text("Play/pause").clicked() {
+                    self.playing = !self.playing;
+                    self.frame_elapsed = 0.0;
+                }
+                if ui.button("⏭").on_hover_text("Next frame").clicked() {
+                    self.playing = false;
+                    self.cur_frame = self.cur_frame % self.num_frames + 1;
+                }
+                ui.checkbox(&mut self.looping, "Loop");
+                ui.add(
+                    egui::DragValue::new(&mut self.playback_fps)
+                        .range(1..=60)
+                        .suffix(" fps"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.num_frames)
+                        .range(1..=256)
+                        .prefix("Frames: "),
+                )
+                .on_hover_text("Sprite frames sampled evenly over the clip");
+            });
+            self.cur_frame = self.cur_frame.clamp(1, self.num_frames);
+        });
+    }
+
+    /// Replace the current palette with one generated from the rendered model, keeping locked
+    /// entries
+    fn generate_palette(&mut self) {
+        let locked: Vec<palette::Color> = match &self.palette {
+            Some(palette) => palette
+                .colors
+                .iter()
+                .zip(&self.locked_colors)
+                .filter(|(_, locked)| **locked)
+                .map(|(color, _)| *color)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let renderer = &mut self.render_viewport.renderer;
+        let palette = renderer.extract_palette(self.palette_method, self.palette_count, &locked);
+
+        self.locked_colors = palette.colors.iter().map(|c| locked.contains(c)).collect();
+        self.palette_status = format!("Generated {} colors", palette.len());
+        self.use_palette = true;
+        renderer.set_palette(Some(&palette));
+        self.palette = Some(palette);
     }
 }
 
@@ -115,6 +800,15 @@ impl eframe::App for App {
     }
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.playing && self.animation.is_some() {
+            self.advance_playback(ctx.input(|i| i.stable_dt));
+            ctx.request_repaint();
+        }
+        self.apply_pose();
+        // The viewports are drawn before the controls, so a pose changed below is only shown
+        // on the next frame
+        let posed = (self.animation, self.cur_frame, self.num_frames);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             Flex::vertical()
                 .w_full()
@@ -164,6 +858,10 @@ impl eframe::App for App {
                                     .grow_items(1.0)
                                     .align_items(egui_flex::FlexAlign::Stretch)
                                     .show(ui, |flex| {
+                                        flex.add_ui(egui_flex::item(), |ui| {
+                                            self.render_controls(ui);
+                                            self.render_timeline_controls(ui);
+                                        });
                                         //ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
                                         let timeline = egui::Slider::new(
                                             &mut self.cur_frame,
@@ -176,5 +874,9 @@ impl eframe::App for App {
                     );
                 });
         });
+
+        if (self.animation, self.cur_frame, self.num_frames) != posed {
+            ctx.request_repaint();
+        }
     }
 }
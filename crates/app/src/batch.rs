@@ -0,0 +1,341 @@
+use std::path::{Path, PathBuf};
+
+/// Options shared by the batch subcommands
+#[derive(clap::Args, Debug)]
+pub struct RenderOptions {
+    /// Path to gltf/glb file to be rendered
+    #[arg(short, long)]
+    gltf: String,
+
+    /// Width and height of the full resolution render the sprite is pixelized from
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    render_size: u32,
+
+    /// Width and height of the pixelated output
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u32).range(1..))]
+    pixel_size: u32,
+
+    /// Palette file (.gpl, .hex, .pal or .png swatch) to quantize the pixelated output to
+    #[arg(long)]
+    palette: Option<String>,
+
+    /// Comma separated model rotations about the vertical axis, in degrees
+    #[arg(
+        long,
+        value_delimiter = ',',
+        allow_negative_numbers = true,
+        default_value = "0"
+    )]
+    angles: Vec<f32>,
+
+    /// Render a turntable of this many equally spaced facings (eg 4, 8 or 16) instead of
+    /// `--angles`.  Sprites are labelled by compass direction, N, NE, E...
+    #[arg(long, conflicts_with = "angles")]
+    directions: Option<u32>,
+
+    /// Model rotation of the N facing of a turntable, in degrees.  The default turns GLTF
+    /// models, which face +Z, away from the camera.
+    #[arg(
+        long,
+        allow_negative_numbers = true,
+        default_value_t = render::Turntable::DEFAULT_START_ANGLE
+    )]
+    start_angle: f32,
+
+    /// Orthographic camera preset: isometric, dimetric, top-down, side or three-quarter
+    #[arg(long)]
+    camera: Option<render::CameraPreset>,
+
+    /// World units covered by each output pixel.  Selects an orthographic projection, fitted to
+    /// the model if a camera preset is given without it
+    #[arg(long)]
+    units_per_pixel: Option<f32>,
+
+    /// Snap the model's root motion to whole output pixels (orthographic projections only)
+    #[arg(long)]
+    snap_root_motion: bool,
+
+    /// Name of the animation clip to pose the model with, eg Walk
+    #[arg(long)]
+    animation: Option<String>,
+
+    /// Number of frames sampled evenly over the animation clip
+    #[arg(long, default_value_t = 1)]
+    frames: u32,
+
+    /// Comma separated companion maps written next to each sprite or sheet: normal, depth,
+    /// albedo, mesh-id, material-id
+    #[arg(long, value_delimiter = ',')]
+    maps: Vec<render::SpriteMap>,
+
+    /// Only use a software (CPU) adapter, for reproducible output on machines without a GPU
+    #[arg(long)]
+    software: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Render a sprite per angle and animation frame to PNG
+    Render {
+        #[command(flatten)]
+        options: RenderOptions,
+
+        /// Output PNG.  With several angles or frames those are appended to the file name, eg
+        /// sprite_90_3.png
+        #[arg(short, long, default_value = "sprite.png")]
+        output: PathBuf,
+    },
+    /// Render all angles and animation frames into a PNG sprite sheet with frame metadata next
+    /// to it
+    Sheet {
+        #[command(flatten)]
+        options: RenderOptions,
+
+        #[command(flatten)]
+        sheet: SheetArgs,
+
+        /// Output PNG, metadata files are written next to it named after each format
+        #[arg(short, long, default_value = "sheet.png")]
+        output: PathBuf,
+    },
+}
+
+/// Sprite sheet layout options
+#[derive(clap::Args, Debug)]
+pub struct SheetArgs {
+    /// Frame layout: grid (one row per direction) or maxrects (tight packing)
+    #[arg(long, default_value = "grid")]
+    packing: sprite::Packing,
+
+    /// Transparent pixels between frames
+    #[arg(long, default_value_t = 1)]
+    padding: u32,
+
+    /// Crop transparent borders from each frame
+    #[arg(long)]
+    trim: bool,
+
+    /// Sheet width for maxrects packing, chosen from the frame area if not given
+    #[arg(long)]
+    max_width: Option<u32>,
+
+    /// Duration of each frame in milliseconds.  Defaults to the animation clip length divided by
+    /// the frame count, or 100 for stills.
+    #[arg(long)]
+    frame_duration: Option<u32>,
+
+    /// Comma separated metadata formats: json, aseprite, godot, texturepacker-json,
+    /// texturepacker-xml, unity
+    #[arg(long, value_delimiter = ',', default_value = "json")]
+    format: Vec<sprite::Format>,
+}
+
+impl SheetArgs {
+    fn options(&self) -> sprite::SheetOptions {
+        sprite::SheetOptions {
+            packing: self.packing,
+            padding: self.padding,
+            trim: self.trim,
+            max_width: self.max_width,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn run(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Render { options, output } => {
+            let sprites = render_frames(&options)?;
+            let single = sprites.len() == 1;
+            for sprite in sprites {
+                let path = if single {
+                    output.clone()
+                } else {
+                    with_suffix(&output, &sprite.frame_suffix(options.frames > 1))
+                };
+                sprite.image.save(&path)?;
+                println!("Wrote {}", path.display());
+                for (map, image) in options.maps.iter().zip(&sprite.maps) {
+                    let map_path = with_suffix(&path, map.name());
+                    image.save(&map_path)?;
+                    println!("Wrote {}", map_path.display());
+                }
+            }
+        }
+        Command::Sheet {
+            options,
+            sheet,
+            output,
+        } => {
+            let mut sprites = render_frames(&options)?;
+            // Companion images per map, each parallel to the frames
+            let maps: Vec<Vec<image::RgbaImage>> = (0..options.maps.len())
+                .map(|i| {
+                    sprites
+                        .iter_mut()
+                        .map(|sprite| std::mem::take(&mut sprite.maps[i]))
+                        .collect()
+                })
+                .collect();
+            let frames: Vec<sprite::Frame> = sprites
+                .into_iter()
+                .map(|sprite| sprite::Frame {
+                    duration_ms: sheet.frame_duration.unwrap_or(sprite.duration_ms),
+                    image: sprite.image,
+                    animation: options.animation.clone().unwrap_or_default(),
+                    direction: sprite.direction,
+                    index: sprite.index,
+                })
+                .collect();
+            let packed = sprite::Sheet::pack(&frames, &sheet.options())?;
+            packed.save(&output, &sheet.format)?;
+            println!("Wrote {}", output.display());
+            for format in &sheet.format {
+                println!("Wrote {}", format.metadata_path(&output).display());
+            }
+            for (map, images) in options.maps.iter().zip(&maps) {
+                let path = sprite::Sheet::companion_path(&output, map.name());
+                packed.companion(images)?.save(&path)?;
+                println!("Wrote {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// One rendered sprite
+struct RenderedFrame {
+    /// Turntable compass label, or the model rotation in degrees
+    direction: String,
+    /// Frame number within the animation
+    index: u32,
+    /// Time until the next animation frame
+    duration_ms: u32,
+    image: image::RgbaImage,
+    /// Companion maps in `RenderOptions::maps` order
+    maps: Vec<image::RgbaImage>,
+}
+
+impl RenderedFrame {
+    /// File name suffix, eg `90` or `NE_3` with `with_index` set
+    fn frame_suffix(&self, with_index: bool) -> String {
+        if with_index {
+            format!("{}_{}", self.direction, self.index)
+        } else {
+            self.direction.clone()
+        }
+    }
+}
+
+/// Render the model once per requested angle (or turntable facing) and animation frame,
+/// angle-major
+fn render_frames(
+    options: &RenderOptions,
+) -> Result<Vec<RenderedFrame>, Box<dyn std::error::Error>> {
+    let (device, queue) = render::headless::request_device(options.software)?;
+    let model = model::Model::from_gltf(&options.gltf)
+        .map_err(|e| format!("Failed to load '{}': {}", options.gltf, e))?;
+
+    let animation = match &options.animation {
+        Some(name) => {
+            let idx = model.find_animation(name).ok_or_else(|| {
+                let names: Vec<&str> = model.animations.iter().map(|a| a.name.as_str()).collect();
+                format!(
+                    "No animation '{}' in '{}', available: {}",
+                    name,
+                    options.gltf,
+                    names.join(", ")
+                )
+            })?;
+            Some((idx, model.animations[idx].duration))
+        }
+        None => None,
+    };
+
+    let mut renderer = render::Renderer::new(
+        &[options.render_size, options.render_size],
+        device,
+        queue,
+        &model,
+    );
+    renderer.set_pixel_size(&[options.pixel_size, options.pixel_size]);
+    if let Some(units_per_pixel) = options.units_per_pixel {
+        renderer.set_projection(render::Projection::Orthographic { units_per_pixel });
+    }
+    if let Some(preset) = options.camera {
+        renderer.set_camera_preset(preset);
+    }
+    renderer.set_snap_root_motion(options.snap_root_motion);
+    if let Some(path) = &options.palette {
+        let palette = palette::Palette::load(path)
+            .map_err(|e| format!("Failed to load palette '{}': {}", path, e))?;
+        renderer.set_palette(Some(&palette));
+    }
+
+    // Frames split the clip into equal steps, the last frame loops back to the first
+    let frame_count = options.frames.max(1);
+    let step = animation.map_or(0.0, |(_, duration)| duration / frame_count as f32);
+    let duration_ms = if animation.is_some() {
+        (step * 1000.0).round() as u32
+    } else {
+        100
+    };
+
+    let turntable = options
+        .directions
+        .map(|directions| render::Turntable::new(directions, options.start_angle));
+
+    // Every facing of each animation frame, frame-major
+    let mut poses = Vec::with_capacity(frame_count as usize);
+    for index in 0..frame_count {
+        if let Some((animation, _)) = animation {
+            renderer.set_animation_time(animation, index as f32 * step);
+        }
+        let facings: Vec<render::TurntableFrame> = match &turntable {
+            Some(turntable) => renderer.render_turntable(turntable, &options.maps),
+            None => options
+                .angles
+                .iter()
+                .map(|&angle| {
+                    renderer.set_model_yaw(angle);
+                    render::TurntableFrame {
+                        direction: angle.to_string(),
+                        yaw: angle,
+                        image: renderer.render_to_image(),
+                        maps: options
+                            .maps
+                            .iter()
+                            .map(|&map| renderer.read_map_pixels(map))
+                            .collect(),
+                    }
+                })
+                .collect(),
+        };
+        poses.push(facings.into_iter());
+    }
+
+    let facing_count = poses.first().map_or(0, |facings| facings.len());
+    let mut frames = Vec::with_capacity(facing_count * poses.len());
+    for _ in 0..facing_count {
+        for (index, facings) in poses.iter_mut().enumerate() {
+            let facing = facings.next().unwrap();
+            frames.push(RenderedFrame {
+                direction: facing.direction,
+                index: index as u32,
+                duration_ms,
+                image: facing.image,
+                maps: facing.maps,
+            });
+        }
+    }
+    Ok(frames)
+}
+
+/// `dir/name.ext` -> `dir/name_<suffix>.ext`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map_or("png".into(), |e| e.to_string_lossy());
+    path.with_file_name(format!("{}_{}.{}", stem, suffix, ext))
+}
@@ -4,12 +4,21 @@
 use clap::Parser;
 
 mod app;
+mod batch;
 
 fn main() -> eframe::Result {
-    let args = app::Args::parse();
+    let mut args = app::Args::parse();
 
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    if let Some(command) = args.command.take() {
+        if let Err(e) = batch::run(command) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_resizable(false)
@@ -0,0 +1,262 @@
+//! Keyframe animation clips loaded from glTF and evaluated into node poses.
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::{Node, Transform};
+
+/// How values between two keyframes are computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Hold the previous keyframe value
+    Step,
+    /// Linear interpolation, spherical for rotations
+    Linear,
+    /// Cubic Hermite spline with per-keyframe in and out tangents
+    CubicSpline,
+}
+
+impl From<gltf::animation::Interpolation> for Interpolation {
+    fn from(interpolation: gltf::animation::Interpolation) -> Self {
+        match interpolation {
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        }
+    }
+}
+
+/// Node property driven by a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+    /// Morph target weights of the node's mesh
+    MorphWeights,
+}
+
+/// Keyframe times and values.
+///
+/// `values` holds `stride` floats per keyframe (3 for translation and scale, 4 for a rotation
+/// quaternion, one per morph target for weights).  With `Interpolation::CubicSpline` each
+/// keyframe stores three elements: in-tangent, value, out-tangent.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds, increasing
+    pub times: Vec<f32>,
+    pub values: Vec<f32>,
+    pub stride: usize,
+}
+
+impl Sampler {
+    /// Keyframe value `idx`, skipping the tangents of cubic splines
+    fn value(&self, idx: usize) -> &[f32] {
+        self.element(idx, 1)
+    }
+
+    /// Element `offset` (0 in-tangent, 1 value, 2 out-tangent) of keyframe `idx`
+    fn element(&self, idx: usize, offset: usize) -> &[f32] {
+        let start = match self.interpolation {
+            Interpolation::CubicSpline => (idx * 3 + offset) * self.stride,
+            _ => idx * self.stride,
+        };
+        &self.values[start..start + self.stride]
+    }
+
+    /// Evaluate the sampler at time `t` into `out` (`stride` floats).  Times outside the
+    /// keyframe range clamp to the first or last keyframe.  With `spherical` set values are
+    /// treated as quaternions and the result is normalized.
+    pub fn sample(&self, t: f32, spherical: bool, out: &mut [f32]) {
+        let last = self.times.len() - 1;
+        if t <= self.times[0] || last == 0 {
+            out.copy_from_slice(self.value(0));
+            return;
+        }
+        if t >= self.times[last] {
+            out.copy_from_slice(self.value(last));
+            return;
+        }
+
+        // Last keyframe at or before t
+        let k = self.times.partition_point(|&time| time <= t) - 1;
+        let dt = self.times[k + 1] - self.times[k];
+        let s = (t - self.times[k]) / dt;
+
+        match self.interpolation {
+            Interpolation::Step => out.copy_from_slice(self.value(k)),
+            Interpolation::Linear => {
+                let (a, b) = (self.value(k), self.value(k + 1));
+                if spherical {
+                    let q = Quat::from_slice(a).slerp(Quat::from_slice(b), s);
+                    q.write_to_slice(out);
+                } else {
+                    for (o, (a, b)) in out.iter_mut().zip(a.iter().zip(b)) {
+                        *o = a + (b - a) * s;
+                    }
+                }
+            }
+            Interpolation::CubicSpline => {
+                let (s2, s3) = (s * s, s * s * s);
+                let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+                let h10 = (s3 - 2.0 * s2 + s) * dt;
+                let h01 = -2.0 * s3 + 3.0 * s2;
+                let h11 = (s3 - s2) * dt;
+
+                let v0 = self.element(k, 1);
+                let b0 = self.element(k, 2);
+                let v1 = self.element(k + 1, 1);
+                let a1 = self.element(k + 1, 0);
+                for (i, o) in out.iter_mut().enumerate() {
+                    *o = h00 * v0[i] + h10 * b0[i] + h01 * v1[i] + h11 * a1[i];
+                }
+                if spherical {
+                    Quat::from_slice(out).normalize().write_to_slice(out);
+                }
+            }
+        }
+    }
+}
+
+/// Animates one property of one node
+#[derive(Debug, Clone)]
+pub struct Channel {
+    /// Index of the target in `Model::nodes`
+    pub node: usize,
+    pub property: Property,
+    pub sampler: Sampler,
+}
+
+/// Named animation clip
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub name: String,
+    pub channels: Vec<Channel>,
+    /// Time of the last keyframe over all channels, in seconds
+    pub duration: f32,
+}
+
+impl Animation {
+    pub(crate) fn from_gltf(animation: &gltf::Animation, buffers: &[gltf::buffer::Data]) -> Self {
+        let mut channels = Vec::new();
+        for channel in animation.channels() {
+            let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(times) = reader.read_inputs() else {
+                continue;
+            };
+            let times: Vec<f32> = times.collect();
+            let Some(outputs) = reader.read_outputs() else {
+                continue;
+            };
+
+            use gltf::animation::util::ReadOutputs;
+            let (property, values, stride): (Property, Vec<f32>, usize) = match outputs {
+                ReadOutputs::Translations(t) => (Property::Translation, t.flatten().collect(), 3),
+                ReadOutputs::Rotations(r) => {
+                    (Property::Rotation, r.into_f32().flatten().collect(), 4)
+                }
+                ReadOutputs::Scales(s) => (Property::Scale, s.flatten().collect(), 3),
+                ReadOutputs::MorphTargetWeights(w) => {
+                    let values: Vec<f32> = w.into_f32().collect();
+                    let interpolation: Interpolation = channel.sampler().interpolation().into();
+                    let elements = match interpolation {
+                        Interpolation::CubicSpline => times.len() * 3,
+                        _ => times.len(),
+                    };
+                    let stride = values.len() / elements.max(1);
+                    (Property::MorphWeights, values, stride)
+                }
+            };
+            if times.is_empty() || stride == 0 {
+                continue;
+            }
+
+            channels.push(Channel {
+                node: channel.target().node().index(),
+                property,
+                sampler: Sampler {
+                    interpolation: channel.sampler().interpolation().into(),
+                    times,
+                    values,
+                    stride,
+                },
+            });
+        }
+
+        let duration = channels
+            .iter()
+            .filter_map(|c| c.sampler.times.last().copied())
+            .fold(0.0, f32::max);
+
+        Self {
+            name: animation.name().map_or_else(
+                || format!("animation_{}", animation.index()),
+                str::to_string,
+            ),
+            channels,
+            duration,
+        }
+    }
+
+    /// Overwrite the animated properties of `pose` with this clip's values at `t` seconds
+    pub fn sample(&self, t: f32, pose: &mut Pose) {
+        let mut value = [0.0; 4];
+        for channel in &self.channels {
+            let transform = &mut pose.transforms[channel.node];
+            match channel.property {
+                Property::Translation => {
+                    channel.sampler.sample(t, false, &mut value[..3]);
+                    transform.translation = Vec3::from_slice(&value);
+                }
+                Property::Rotation => {
+                    channel.sampler.sample(t, true, &mut value);
+                    transform.rotation = Quat::from_slice(&value);
+                }
+                Property::Scale => {
+                    channel.sampler.sample(t, false, &mut value[..3]);
+                    transform.scale = Vec3::from_slice(&value);
+                }
+                Property::MorphWeights => {
+                    let weights = &mut pose.weights[channel.node];
+                    weights.resize(channel.sampler.stride, 0.0);
+                    channel.sampler.sample(t, false, weights);
+                }
+            }
+        }
+    }
+}
+
+/// Local transform and morph weights of every node in a model
+#[derive(Debug, Clone, Default)]
+pub struct Pose {
+    /// Indexed like `Model::nodes`
+    pub transforms: Vec<Transform>,
+    /// Morph target weights per node, empty for nodes without morph targets
+    pub weights: Vec<Vec<f32>>,
+}
+
+impl Pose {
+    /// Pose holding the nodes' default (rest) transforms and weights
+    pub fn rest(nodes: &[Node]) -> Self {
+        Self {
+            transforms: nodes.iter().map(|n| n.transform).collect(),
+            weights: nodes.iter().map(|n| n.weights.clone()).collect(),
+        }
+    }
+
+    /// Node to model space transform of every node, accumulated from the roots
+    pub fn global_transforms(&self, nodes: &[Node]) -> Vec<Mat4> {
+        let mut globals = vec![Mat4::IDENTITY; nodes.len()];
+        let mut stack: Vec<(usize, Mat4)> = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.parent.is_none())
+            .map(|(idx, _)| (idx, Mat4::IDENTITY))
+            .collect();
+        while let Some((idx, parent)) = stack.pop() {
+            globals[idx] = parent * self.transforms[idx].matrix();
+            stack.extend(nodes[idx].children.iter().map(|&c| (c, globals[idx])));
+        }
+        globals
+    }
+}
@@ -1,9 +1,17 @@
+pub mod animation;
+#[cfg(test)]
+mod tests;
+
 use glam::Mat4;
+use glam::Quat;
 use glam::UVec3;
 use glam::Vec3;
+use glam::Vec4;
 
 use itertools::Itertools;
 
+pub use animation::{Animation, Pose};
+
 /// 3D axis-aligned bounding box.
 #[derive(Copy, Clone)]
 pub struct BoundingBox {
@@ -53,7 +61,10 @@ impl Default for BoundingBox {
 #[derive(Default)]
 pub struct Model {
     pub bbox: BoundingBox,
-    pub skins: Vec<Mesh>,
+    /// Node hierarchy, indexed like the GLTF nodes
+    pub nodes: Vec<Node>,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<Animation>,
     pub meshes: Vec<Mesh>,
     pub materials: Vec<Material>,
     pub textures: Vec<Texture>,
@@ -62,19 +73,39 @@ pub struct Model {
 impl Model {
     /// Convert gltf model to our in-memory model format
     /// Process the GLTF root node, traversing the node tree, accumulating
-    /// transforms and creating pre-transformed meshes
-    pub fn from_gltf(gltf_file: &str) -> Self {
+    /// transforms and creating pre-transformed meshes.  Fails if the file can't be read or
+    /// isn't valid GLTF.
+    pub fn from_gltf(gltf_file: &str) -> Result<Self, gltf::Error> {
         let mut model = Model::default();
 
-        let (document, buffers, images) = gltf::import(gltf_file).expect("Failed to load GLTF");
+        let (document, buffers, images) = gltf::import(gltf_file)?;
+        model.nodes = document.nodes().map(|n| Node::from_gltf(&n)).collect();
+        for node in document.nodes() {
+            for child in node.children() {
+                model.nodes[child.index()].parent = Some(node.index());
+            }
+        }
+
         for root_node in document.nodes() {
-            model.process_node(&root_node, &buffers, &images, glam::Mat4::IDENTITY);
+            if model.nodes[root_node.index()].parent.is_none() {
+                model.process_node(&root_node, &buffers, &images, glam::Mat4::IDENTITY);
+            }
         }
 
-        for material_node in document.materials() {
+        for skin_node in document.skins() {
+            model.skins.push(Skin::from_gltf(&skin_node, &buffers));
+        }
+
+        for animation_node in document.animations() {
             model
-                .materials
-                .push(Material::from_gltf(&material_node, document.textures()));
+                .animations
+                .push(Animation::from_gltf(&animation_node, &buffers));
+        }
+
+        // Primitives without a material use the default material at index 0
+        model.materials.push(Material::default());
+        for material_node in document.materials() {
+            model.materials.push(Material::from_gltf(&material_node));
         }
 
         for texture_node in document.textures() {
@@ -86,7 +117,25 @@ impl Model {
             model.bbox.expand_by_bbox(&mesh.bbox);
         }
 
-        model
+        Ok(model)
+    }
+
+    /// Index of the animation clip called `name`
+    pub fn find_animation(&self, name: &str) -> Option<usize> {
+        self.animations.iter().position(|a| a.name == name)
+    }
+
+    /// Default node transforms and morph weights, as authored in the file
+    pub fn rest_pose(&self) -> Pose {
+        Pose::rest(&self.nodes)
+    }
+
+    /// Pose of animation clip `animation` at `t` seconds.  Nodes the clip doesn't animate keep
+    /// their rest transform.
+    pub fn pose_at(&self, animation: usize, t: f32) -> Pose {
+        let mut pose = self.rest_pose();
+        self.animations[animation].sample(t, &mut pose);
+        pose
     }
 
     fn process_node(
@@ -100,11 +149,10 @@ impl Model {
         let transform = transform * node_transform;
 
         if let Some(mesh_node) = node.mesh() {
-            println!("Found mesh '{}'", mesh_node.name().unwrap_or("<UNNAMED>"));
-            println!("\txform: {:?}", node_transform);
-            println!("\tprim count: {}", mesh_node.primitives().len());
-            self.meshes
-                .push(Mesh::from_gltf(&mesh_node, node.skin(), buffers, transform));
+            let mut mesh = Mesh::from_gltf(&mesh_node, buffers, transform);
+            mesh.node = node.index();
+            mesh.skin = node.skin().map(|skin| skin.index());
+            self.meshes.push(mesh);
         }
 
         for child in node.children() {
@@ -113,13 +161,106 @@ impl Model {
     }
 }
 
-/// Skin
-#[derive(Default)]
-pub struct Skin {}
+/// Translation, rotation and scale of a node relative to its parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// Element of the GLTF scene graph.  Kept so animations can move nodes (and the joints of
+/// skins) after the meshes have been flattened into `Model::meshes`.
+#[derive(Debug, Clone, Default)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Rest transform
+    pub transform: Transform,
+    /// Default morph target weights of the node's mesh
+    pub weights: Vec<f32>,
+}
+
+impl Node {
+    fn from_gltf(node: &gltf::Node) -> Self {
+        let (translation, rotation, scale) = node.transform().decomposed();
+        let weights = node
+            .weights()
+            .or_else(|| node.mesh().and_then(|m| m.weights()))
+            .map(|w| w.to_vec())
+            .unwrap_or_default();
+        Self {
+            name: node
+                .name()
+                .map_or_else(|| format!("node_{}", node.index()), str::to_string),
+            parent: None,
+            children: node.children().map(|c| c.index()).collect(),
+            transform: Transform {
+                translation: Vec3::from_array(translation),
+                rotation: Quat::from_array(rotation),
+                scale: Vec3::from_array(scale),
+            },
+            weights,
+        }
+    }
+}
+
+/// Joints binding a mesh to a skeleton
+#[derive(Debug, Clone, Default)]
+pub struct Skin {
+    pub name: String,
+    /// Node index of each joint.  Vertex JOINTS attributes index into this list.
+    pub joints: Vec<usize>,
+    /// Model to joint space transform of each joint in the bind pose
+    pub inverse_bind_matrices: Vec<Mat4>,
+    /// Common root node of the joint hierarchy, if given
+    pub skeleton: Option<usize>,
+}
 
 impl Skin {
-    fn new(_skin_node: &gltf::Skin) -> Self {
-        Self {}
+    fn from_gltf(skin_node: &gltf::Skin, buffers: &[gltf::buffer::Data]) -> Self {
+        let joints: Vec<usize> = skin_node.joints().map(|j| j.index()).collect();
+        let reader = skin_node.reader(|buffer| Some(&buffers[buffer.index()]));
+        let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+            Some(matrices) => matrices.map(|m| Mat4::from_cols_array_2d(&m)).collect(),
+            None => vec![Mat4::IDENTITY; joints.len()],
+        };
+        Self {
+            name: skin_node
+                .name()
+                .map_or_else(|| format!("skin_{}", skin_node.index()), str::to_string),
+            joints,
+            inverse_bind_matrices,
+            skeleton: skin_node.skeleton().map(|s| s.index()),
+        }
+    }
+
+    /// Skinning matrix of each joint: its global transform in `globals` (see
+    /// `Pose::global_transforms`) times its inverse bind matrix.  These take bind pose vertices
+    /// straight to model space, the skinned mesh's own node transform is not applied.
+    pub fn joint_matrices(&self, globals: &[Mat4]) -> Vec<Mat4> {
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind)| globals[joint] * *inverse_bind)
+            .collect()
     }
 }
 /// 3D triangle mesh model.
@@ -139,14 +280,16 @@ pub struct Mesh {
     /// List of mesh primitives with pre-transformed vertex data
     pub primitives: Vec<Primitive>,
 
-    /// Options animation skin
-    pub skin: Option<Skin>,
+    /// Index of the node in `Model::nodes` the mesh is attached to
+    pub node: usize,
+
+    /// Index of the animation skin in `Model::skins`
+    pub skin: Option<usize>,
 }
 
 impl Mesh {
     fn from_gltf(
         mesh_node: &gltf::Mesh,
-        skin_node: Option<gltf::Skin>,
         buffers: &[gltf::buffer::Data],
         transform: glam::Mat4,
     ) -> Self {
@@ -156,10 +299,6 @@ impl Mesh {
         };
 
         for primitive in mesh_node.primitives() {
-            for attr in primitive.attributes() {
-                println!("\t\tattr: {}", attr.0.to_string());
-            }
-
             // Transform the bounding box to world space and store
             let bbox_gltf = primitive.bounding_box();
             let bbox = BoundingBox::new(
@@ -167,13 +306,11 @@ impl Mesh {
                 glam::Vec3::from_slice(&bbox_gltf.max),
             );
             mesh.bbox.expand_by_bbox(&bbox);
-            println!("\txformed prim bbox: {:?} - {:?}", bbox.min, bbox.max);
 
             let mut prim = Primitive::default();
 
             let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
             if let Some(p) = reader.read_positions() {
-                println!("\tP len: {}", p.len());
                 prim.positions = p.map(|x| Vec3::new(x[0], x[1], x[2])).collect();
             }
 
@@ -183,64 +320,56 @@ impl Mesh {
                     .tuples()
                     .map(|(x, y, z)| UVec3::new(x, y, z))
                     .collect();
-                println!("\tI len: {}", prim.indices.len());
-            } else {
-                println!("\tI not found");
             }
 
             if let Some(n) = reader.read_normals() {
-                println!("\tN len: {}", n.len());
                 prim.normals = n.map(|x| Vec3::new(x[0], x[1], x[2])).collect();
-            } else {
-                println!("\tN not found");
             }
-            if let Some(cenum) = reader.read_colors(0) {
-                match cenum {
-                    gltf::mesh::util::ReadColors::RgbU8(i) => {
-                        println!("RgbU8 len: {}", i.len());
-                    }
-                    gltf::mesh::util::ReadColors::RgbU16(i) => {
-                        println!("RgbU16 len: {}", i.len());
-                    }
-                    gltf::mesh::util::ReadColors::RgbF32(i) => {
-                        println!("RgbF32 len: {}", i.len());
-                    }
-                    gltf::mesh::util::ReadColors::RgbaU8(i) => {
-                        println!("RgbaU8 len: {}", i.len());
-                    }
-                    gltf::mesh::util::ReadColors::RgbaU16(i) => {
-                        println!("RgbaU16 len: {}", i.len());
-                    }
-                    gltf::mesh::util::ReadColors::RgbaF32(i) => {
-                        println!("RgbaF32 len: {}", i.len());
-                    }
-                }
-            } else {
-                println!("\tC not found");
+            // Deltas missing from a target are stored as zeros so every target covers all vertices
+            let vertex_count = prim.positions.len();
+            for (positions, normals, tangents) in reader.read_morph_targets() {
+                let deltas = |delta: Option<Vec<Vec3>>| {
+                    delta.unwrap_or_else(|| vec![Vec3::ZERO; vertex_count])
+                };
+                prim.morph_targets.push(MorphTarget {
+                    positions: deltas(positions.map(|d| d.map(Vec3::from_array).collect())),
+                    normals: deltas(normals.map(|d| d.map(Vec3::from_array).collect())),
+                    tangents: deltas(tangents.map(|d| d.map(Vec3::from_array).collect())),
+                });
+            }
+            if !prim.morph_targets.is_empty() {
+                prim.morph_weights = mesh_node
+                    .weights()
+                    .map(|w| w.to_vec())
+                    .unwrap_or_else(|| vec![0.0; prim.morph_targets.len()]);
             }
 
-            if let Some(uvenum) = reader.read_tex_coords(0) {
-                match uvenum {
-                    gltf::mesh::util::ReadTexCoords::U8(i) => {
-                        println!("UV U8 len: {}", i.len());
-                    }
-                    gltf::mesh::util::ReadTexCoords::U16(i) => {
-                        println!("UV U16 len: {}", i.len());
-                    }
-                    gltf::mesh::util::ReadTexCoords::F32(i) => {
-                        println!("UV F32 len: {}", i.len());
-                    }
-                }
-            } else {
-                println!("\tUV not found");
+            let mut set = 0;
+            while let (Some(j), Some(w)) = (reader.read_joints(set), reader.read_weights(set)) {
+                prim.joints
+                    .push(j.into_u16().map(glam::U16Vec4::from_array).collect());
+                prim.weights
+                    .push(w.into_f32().map(glam::Vec4::from_array).collect());
+                set += 1;
+            }
+
+            if let Some(c) = reader.read_colors(0) {
+                prim.colors = c.into_rgba_f32().map(glam::Vec4::from_array).collect();
+            }
+
+            if let Some(t) = reader.read_tangents() {
+                prim.tangents = t.map(glam::Vec4::from_array).collect();
             }
-            mesh.primitives.push(prim);
 
-            if let Some(skin_node) = skin_node.as_ref() {
-                mesh.skin = Some(Skin::new(skin_node));
-            } else {
-                mesh.skin = None;
+            let mut set = 0;
+            while let Some(uv) = reader.read_tex_coords(set) {
+                prim.texcoords
+                    .push(uv.into_f32().map(glam::Vec2::from_array).collect());
+                set += 1;
             }
+
+            prim.material_idx = primitive.material().index().map_or(0, |idx| idx as u32 + 1);
+            mesh.primitives.push(prim);
         }
         mesh
     }
@@ -250,7 +379,8 @@ impl Mesh {
 /// with a single `Material`.
 #[derive(Default)]
 pub struct Primitive {
-    /// Index in model's list of Materials -- zero being default material
+    /// Index in model's list of Materials -- zero being default material, GLTF material `i` is
+    /// at `i + 1`
     pub material_idx: u32,
 
     /// List of vertex index triples.
@@ -267,7 +397,8 @@ pub struct Primitive {
 
     /// TEXCOORD_<N> vertex attributes.
     ///
-    /// 2D texture coordinates.  A single model may have multiple tex coord sets.
+    /// 2D texture coordinates.  A single model may have multiple tex coord sets.  Normalized
+    /// integer coordinates are converted to f32 in [0, 1].
     pub texcoords: Vec<Vec<glam::Vec2>>,
 
     /// NORMAL vertex attribute.
@@ -294,10 +425,29 @@ pub struct Primitive {
     /// weights may be zero. A single model may have multiple weight/joint sets.
     pub weights: Vec<Vec<glam::Vec4>>,
 
-    /// COLORS_<N> vertex attributes.
+    /// COLOR_0 vertex attribute.
     ///
-    /// List of vertex colors.  All colors upconverted to f32 RGBA (eg, from U8)
+    /// List of vertex colors.  All colors upconverted to f32 RGBA (eg, from U8), with alpha 1 for
+    /// RGB colors.
     pub colors: Vec<glam::Vec4>,
+
+    /// Morph targets (blend shapes).
+    ///
+    /// The morphed vertex attributes are the base attributes plus the sum of each target's
+    /// deltas scaled by its weight.
+    pub morph_targets: Vec<MorphTarget>,
+
+    /// Default weight of each morph target, from the GLTF mesh
+    pub morph_weights: Vec<f32>,
+}
+
+/// Per-vertex attribute displacements of a single morph target.  Each list has one entry per
+/// vertex, zero for attributes the target doesn't displace.
+#[derive(Default, Clone)]
+pub struct MorphTarget {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub tangents: Vec<Vec3>,
 }
 
 pub mod texture {
@@ -325,6 +475,39 @@ pub mod texture {
         Linear = 2,
     }
 
+    impl From<gltf::texture::WrappingMode> for WrappingMode {
+        fn from(mode: gltf::texture::WrappingMode) -> Self {
+            match mode {
+                gltf::texture::WrappingMode::ClampToEdge => WrappingMode::ClampToEdge,
+                gltf::texture::WrappingMode::MirroredRepeat => WrappingMode::MirroredRepeat,
+                gltf::texture::WrappingMode::Repeat => WrappingMode::Repeat,
+            }
+        }
+    }
+
+    impl From<gltf::texture::MinFilter> for MinFilter {
+        fn from(filter: gltf::texture::MinFilter) -> Self {
+            use gltf::texture::MinFilter as F;
+            match filter {
+                F::Nearest => MinFilter::Nearest,
+                F::Linear => MinFilter::Linear,
+                F::NearestMipmapNearest => MinFilter::NearestMipmapNearest,
+                F::LinearMipmapNearest => MinFilter::LinearMipmapNearest,
+                F::NearestMipmapLinear => MinFilter::NearestMipmapLinear,
+                F::LinearMipmapLinear => MinFilter::LinearMipmapLinear,
+            }
+        }
+    }
+
+    impl From<gltf::texture::MagFilter> for MagFilter {
+        fn from(filter: gltf::texture::MagFilter) -> Self {
+            match filter {
+                gltf::texture::MagFilter::Nearest => MagFilter::Nearest,
+                gltf::texture::MagFilter::Linear => MagFilter::Linear,
+            }
+        }
+    }
+
     #[derive(Default)]
     pub struct Sampler {
         pub min_filter: Option<MinFilter>,
@@ -335,36 +518,172 @@ pub mod texture {
 }
 
 pub struct Texture {
-    sampler: texture::Sampler,
+    pub image: image::DynamicImage,
+    pub sampler: texture::Sampler,
 }
 
 impl Texture {
-    fn from_gltf(texture: &gltf::Texture, images: &Vec<gltf::image::Data>) -> Self {
-        Self {
-            sampler: texture::Sampler::default(),
+    fn from_gltf(texture: &gltf::Texture, images: &[gltf::image::Data]) -> Self {
+        let gltf_sampler = texture.sampler();
+        let sampler = texture::Sampler {
+            min_filter: gltf_sampler.min_filter().map(Into::into),
+            max_filter: gltf_sampler.mag_filter().map(Into::into),
+            wrap_s: gltf_sampler.wrap_s().into(),
+            wrap_t: gltf_sampler.wrap_t().into(),
+        };
+
+        let data = &images[texture.source().index()];
+        let image = convert_image(data).unwrap_or_else(|| {
+            eprintln!(
+                "Unable to convert image {} ({:?}, {}x{}), using white",
+                texture.source().index(),
+                data.format,
+                data.width,
+                data.height
+            );
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                1,
+                1,
+                image::Rgba([255; 4]),
+            ))
+        });
+
+        Self { image, sampler }
+    }
+}
+
+/// Wrap decoded GLTF pixels in an image of the matching channel layout.  Single and dual
+/// channel images are treated as luminance (and alpha).  16-bit and float data is stored in
+/// native byte order by the GLTF importer.
+fn convert_image(data: &gltf::image::Data) -> Option<image::DynamicImage> {
+    use gltf::image::Format;
+    use image::{DynamicImage, ImageBuffer};
+
+    let (w, h) = (data.width, data.height);
+    let pixels = &data.pixels;
+    let u16s = || -> Vec<u16> {
+        pixels
+            .chunks_exact(2)
+            .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+            .collect()
+    };
+    let f32s = || -> Vec<f32> {
+        pixels
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    };
+
+    let image = match data.format {
+        Format::R8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, pixels.clone())?),
+        Format::R8G8 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(w, h, pixels.clone())?),
+        Format::R8G8B8 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, pixels.clone())?),
+        Format::R8G8B8A8 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, pixels.clone())?),
+        Format::R16 => DynamicImage::ImageLuma16(ImageBuffer::from_raw(w, h, u16s())?),
+        Format::R16G16 => DynamicImage::ImageLumaA16(ImageBuffer::from_raw(w, h, u16s())?),
+        Format::R16G16B16 => DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, u16s())?),
+        Format::R16G16B16A16 => DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, u16s())?),
+        Format::R32G32B32FLOAT => DynamicImage::ImageRgb32F(ImageBuffer::from_raw(w, h, f32s())?),
+        Format::R32G32B32A32FLOAT => {
+            DynamicImage::ImageRgba32F(ImageBuffer::from_raw(w, h, f32s())?)
+        }
+    };
+    Some(image)
+}
+
+/// How the alpha of the base color is interpreted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlphaMode {
+    /// Alpha is ignored
+    #[default]
+    Opaque,
+    /// Fully opaque where alpha is at least `Material::alpha_cutoff`, discarded elsewhere
+    Mask,
+    /// Blended with the background
+    Blend,
+}
+
+impl From<gltf::material::AlphaMode> for AlphaMode {
+    fn from(mode: gltf::material::AlphaMode) -> Self {
+        match mode {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
         }
     }
 }
 
+/// GLTF metallic-roughness material.  Texture indices refer to `Model::textures`, factors
+/// multiply the texture values.
 pub struct Material {
-    base_color: Vec3,
-    base_color_tex: Option<usize>,
+    /// Linear RGBA
+    pub base_color: Vec4,
+    pub base_color_tex: Option<usize>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Roughness in the green channel, metalness in blue
+    pub metallic_roughness_tex: Option<usize>,
+    /// Tangent space normal map
+    pub normal_tex: Option<usize>,
+    pub normal_scale: f32,
+    /// Ambient occlusion in the red channel
+    pub occlusion_tex: Option<usize>,
+    pub occlusion_strength: f32,
+    /// Linear RGB
+    pub emissive: Vec3,
+    pub emissive_tex: Option<usize>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    /// Back faces are rendered (with flipped normals) instead of culled
+    pub double_sided: bool,
+}
+
+impl Default for Material {
+    /// GLTF default material: white, fully metallic and rough, untextured
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::ONE,
+            base_color_tex: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            metallic_roughness_tex: None,
+            normal_tex: None,
+            normal_scale: 1.0,
+            occlusion_tex: None,
+            occlusion_strength: 1.0,
+            emissive: Vec3::ZERO,
+            emissive_tex: None,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+        }
+    }
 }
 
 impl Material {
-    fn from_gltf(material: &gltf::Material, mut textures: gltf::iter::Textures) -> Self {
+    fn from_gltf(material: &gltf::Material) -> Self {
         let pbr = &material.pbr_metallic_roughness();
-        let base_color = Vec3::from_slice(&pbr.base_color_factor());
-        let mut base_color_tex = None;
-        if let Some(tex_info) = &pbr.base_color_texture() {
-            let tex_idx = textures.position(|tex| tex.index() == tex_info.texture().index());
-            base_color_tex = tex_idx;
-            println!("Base color tex: {:?}", base_color_tex);
-        }
+        let base_color_tex = pbr.base_color_texture().map(|t| t.texture().index());
+        let normal = material.normal_texture();
+        let occlusion = material.occlusion_texture();
 
         Self {
-            base_color,
+            base_color: Vec4::from_array(pbr.base_color_factor()),
             base_color_tex,
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            metallic_roughness_tex: pbr
+                .metallic_roughness_texture()
+                .map(|t| t.texture().index()),
+            normal_tex: normal.as_ref().map(|t| t.texture().index()),
+            normal_scale: normal.as_ref().map_or(1.0, |t| t.scale()),
+            occlusion_tex: occlusion.as_ref().map(|t| t.texture().index()),
+            occlusion_strength: occlusion.as_ref().map_or(1.0, |t| t.strength()),
+            emissive: Vec3::from_array(material.emissive_factor()),
+            emissive_tex: material.emissive_texture().map(|t| t.texture().index()),
+            alpha_mode: material.alpha_mode().into(),
+            alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+            double_sided: material.double_sided(),
         }
     }
 }
@@ -0,0 +1,303 @@
+use glam::{U16Vec4, Vec2, Vec3, Vec4};
+use serde_json::{json, Value};
+
+use crate::{AlphaMode, Model};
+
+const BYTE: u32 = 5121;
+const SHORT: u32 = 5123;
+const FLOAT: u32 = 5126;
+
+/// Builds a single-primitive GLTF file with its attributes in a separate .bin buffer
+#[derive(Default)]
+struct Fixture {
+    buffer: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+    attributes: serde_json::Map<String, Value>,
+    material: Option<usize>,
+    material_def: Option<Value>,
+}
+
+impl Fixture {
+    /// Triangle in the XY plane with only positions
+    fn triangle() -> Self {
+        let mut fixture = Self::default();
+        let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let accessor = fixture.push(&floats(positions.as_flattened()), FLOAT, false, "VEC3", 3);
+        fixture.accessors[accessor]["min"] = json!([0.0, 0.0, 0.0]);
+        fixture.accessors[accessor]["max"] = json!([1.0, 1.0, 0.0]);
+        fixture
+            .attributes
+            .insert("POSITION".to_string(), accessor.into());
+        fixture
+    }
+
+    fn push(
+        &mut self,
+        bytes: &[u8],
+        component_type: u32,
+        normalized: bool,
+        ty: &str,
+        count: usize,
+    ) -> usize {
+        // Accessor data must be aligned to its component size
+        while self.buffer.len() % 4 != 0 {
+            self.buffer.push(0);
+        }
+        self.views.push(json!({
+            "buffer": 0,
+            "byteOffset": self.buffer.len(),
+            "byteLength": bytes.len(),
+        }));
+        self.buffer.extend_from_slice(bytes);
+
+        let mut accessor = json!({
+            "bufferView": self.views.len() - 1,
+            "componentType": component_type,
+            "count": count,
+            "type": ty,
+        });
+        if normalized {
+            accessor["normalized"] = true.into();
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn attribute(
+        mut self,
+        name: &str,
+        bytes: &[u8],
+        component_type: u32,
+        normalized: bool,
+        ty: &str,
+    ) -> Self {
+        let accessor = self.push(bytes, component_type, normalized, ty, 3);
+        self.attributes.insert(name.to_string(), accessor.into());
+        self
+    }
+
+    fn material(mut self, material: usize) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Use `def` as the only material
+    fn material_def(mut self, def: Value) -> Self {
+        self.material = Some(0);
+        self.material_def = Some(def);
+        self
+    }
+
+    /// Write `<name>.gltf` and `<name>.bin` to the temp dir and load them
+    fn load(self, name: &str) -> Model {
+        let dir = std::env::temp_dir().join(format!("model-tests-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut primitive = json!({ "attributes": self.attributes });
+        if let Some(material) = self.material {
+            primitive["material"] = material.into();
+        }
+        let materials: Vec<Value> = match self.material_def {
+            Some(def) => vec![def],
+            None => (0..=self.material.unwrap_or(0))
+                .map(|i| json!({ "name": format!("material_{}", i) }))
+                .collect(),
+        };
+        let gltf = json!({
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{ "primitives": [primitive] }],
+            "materials": materials,
+            "buffers": [{ "byteLength": self.buffer.len(), "uri": format!("{}.bin", name) }],
+            "bufferViews": self.views,
+            "accessors": self.accessors,
+        });
+
+        std::fs::write(dir.join(format!("{}.bin", name)), &self.buffer).unwrap();
+        let path = dir.join(format!("{}.gltf", name));
+        std::fs::write(&path, gltf.to_string()).unwrap();
+        Model::from_gltf(path.to_str().unwrap()).unwrap()
+    }
+}
+
+fn floats(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn shorts(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+#[test]
+fn reads_float_attributes() {
+    let model = Fixture::triangle()
+        .attribute(
+            "NORMAL",
+            &floats(&[0.0, 0.0, 1.0].repeat(3)),
+            FLOAT,
+            false,
+            "VEC3",
+        )
+        .attribute(
+            "TANGENT",
+            &floats(&[1.0, 0.0, 0.0, -1.0].repeat(3)),
+            FLOAT,
+            false,
+            "VEC4",
+        )
+        .attribute(
+            "TEXCOORD_0",
+            &floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0]),
+            FLOAT,
+            false,
+            "VEC2",
+        )
+        .attribute(
+            "TEXCOORD_1",
+            &floats(&[0.5, 0.5, 0.25, 0.5, 0.5, 0.25]),
+            FLOAT,
+            false,
+            "VEC2",
+        )
+        .attribute(
+            "COLOR_0",
+            &floats(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]),
+            FLOAT,
+            false,
+            "VEC3",
+        )
+        .load("float_attributes");
+
+    let prim = &model.meshes[0].primitives[0];
+    assert_eq!(prim.normals, vec![Vec3::Z; 3]);
+    assert_eq!(prim.tangents, vec![Vec4::new(1.0, 0.0, 0.0, -1.0); 3]);
+    assert_eq!(prim.texcoords.len(), 2);
+    assert_eq!(prim.texcoords[0][1], Vec2::new(1.0, 0.0));
+    assert_eq!(prim.texcoords[1][2], Vec2::new(0.5, 0.25));
+    // RGB colors get an opaque alpha
+    assert_eq!(
+        prim.colors,
+        vec![
+            Vec4::new(1.0, 0.0, 0.0, 1.0),
+            Vec4::new(0.0, 1.0, 0.0, 1.0),
+            Vec4::new(0.0, 0.0, 1.0, 1.0),
+        ]
+    );
+}
+
+#[test]
+fn converts_normalized_integer_attributes() {
+    let model = Fixture::triangle()
+        .attribute(
+            "TEXCOORD_0",
+            &[0, 0, 255, 0, 0, 255, 0, 0],
+            BYTE,
+            true,
+            "VEC2",
+        )
+        .attribute(
+            "COLOR_0",
+            &shorts(&[65535, 0, 0, 65535, 0, 65535, 0, 0, 0, 0, 65535, 0]),
+            SHORT,
+            true,
+            "VEC4",
+        )
+        .load("normalized_attributes");
+
+    let prim = &model.meshes[0].primitives[0];
+    assert_eq!(
+        prim.texcoords[0],
+        vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.0, 1.0)
+        ]
+    );
+    assert_eq!(prim.colors[0], Vec4::new(1.0, 0.0, 0.0, 1.0));
+    assert_eq!(prim.colors[1], Vec4::new(0.0, 1.0, 0.0, 0.0));
+    assert_eq!(prim.colors[2], Vec4::new(0.0, 0.0, 1.0, 0.0));
+}
+
+#[test]
+fn reads_joint_and_weight_sets() {
+    let joints_0: [u8; 12] = [0, 1, 0, 0, 1, 2, 0, 0, 2, 0, 0, 0];
+    let joints_1: [u16; 12] = [3, 0, 0, 0, 4, 0, 0, 0, 300, 0, 0, 0];
+    let weights_0: [u8; 12] = [255, 0, 0, 0, 128, 127, 0, 0, 255, 0, 0, 0];
+    let weights_1 = [0.0f32; 12];
+    let model = Fixture::triangle()
+        .attribute("JOINTS_0", &joints_0, BYTE, false, "VEC4")
+        .attribute("WEIGHTS_0", &weights_0, BYTE, true, "VEC4")
+        .attribute("JOINTS_1", &shorts(&joints_1), SHORT, false, "VEC4")
+        .attribute("WEIGHTS_1", &floats(&weights_1), FLOAT, false, "VEC4")
+        .load("joint_sets");
+
+    let prim = &model.meshes[0].primitives[0];
+    assert_eq!(prim.joints.len(), 2);
+    assert_eq!(prim.weights.len(), 2);
+    assert_eq!(prim.joints[0][1], U16Vec4::new(1, 2, 0, 0));
+    assert_eq!(prim.joints[1][2], U16Vec4::new(300, 0, 0, 0));
+    assert_eq!(prim.weights[0][0], Vec4::new(1.0, 0.0, 0.0, 0.0));
+    assert!((prim.weights[0][1].x - 128.0 / 255.0).abs() < 1e-6);
+    assert_eq!(prim.weights[1][0], Vec4::ZERO);
+}
+
+#[test]
+fn material_index_skips_default_material() {
+    let model = Fixture::triangle().material(1).load("material");
+    assert_eq!(model.materials.len(), 3);
+    assert_eq!(model.meshes[0].primitives[0].material_idx, 2);
+
+    let model = Fixture::triangle().load("no_material");
+    assert_eq!(model.meshes[0].primitives[0].material_idx, 0);
+    assert_eq!(model.materials[0].base_color, Vec4::ONE);
+}
+
+#[test]
+fn reads_pbr_material() {
+    let model = Fixture::triangle()
+        .material_def(json!({
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [1.0, 0.5, 0.25, 0.5],
+                "metallicFactor": 0.25,
+                "roughnessFactor": 0.75,
+            },
+            "emissiveFactor": [0.0, 1.0, 0.0],
+            "alphaMode": "MASK",
+            "alphaCutoff": 0.25,
+            "doubleSided": true,
+        }))
+        .load("pbr_material");
+
+    let material = &model.materials[1];
+    assert_eq!(material.base_color, Vec4::new(1.0, 0.5, 0.25, 0.5));
+    assert_eq!(material.metallic, 0.25);
+    assert_eq!(material.roughness, 0.75);
+    assert_eq!(material.emissive, Vec3::Y);
+    assert_eq!(material.alpha_mode, AlphaMode::Mask);
+    assert_eq!(material.alpha_cutoff, 0.25);
+    assert!(material.double_sided);
+    assert!(material.normal_tex.is_none());
+
+    // GLTF defaults
+    let default = &model.materials[0];
+    assert_eq!((default.metallic, default.roughness), (1.0, 1.0));
+    assert_eq!(default.alpha_mode, AlphaMode::Opaque);
+    assert_eq!(default.alpha_cutoff, 0.5);
+    assert!(!default.double_sided);
+}
+
+#[test]
+fn missing_attributes_stay_empty() {
+    let model = Fixture::triangle().load("positions_only");
+    let prim = &model.meshes[0].primitives[0];
+    assert_eq!(prim.positions.len(), 3);
+    assert!(prim.normals.is_empty());
+    assert!(prim.tangents.is_empty());
+    assert!(prim.texcoords.is_empty());
+    assert!(prim.colors.is_empty());
+    assert!(prim.joints.is_empty());
+    assert!(prim.weights.is_empty());
+}
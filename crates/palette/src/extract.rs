@@ -0,0 +1,230 @@
+use crate::{oklab, Color, Palette};
+
+/// Palette generation algorithm
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Method {
+    /// Recursively split the color box with the largest weighted extent at its median
+    #[default]
+    MedianCut,
+    /// Lloyd's k-means, seeded with the median cut result
+    KMeans,
+}
+
+impl Method {
+    pub const ALL: [Method; 2] = [Method::MedianCut, Method::KMeans];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Method::MedianCut => "Median cut",
+            Method::KMeans => "K-means",
+        }
+    }
+}
+
+/// Maximum number of Lloyd iterations for k-means
+const KMEANS_ITERATIONS: usize = 16;
+
+/// Squared OKLab distance below which a color is considered covered by a locked entry
+const LOCKED_EPSILON_SQ: f32 = 1.0e-4;
+
+/// A distinct input color in OKLab with the number of times it occurs
+#[derive(Copy, Clone)]
+struct Sample {
+    lab: glam::Vec3,
+    weight: f32,
+}
+
+/// Generate a palette of at most `count` colors from `colors` (eg, the pixels of a render).
+///
+/// All work is done in OKLab.  `locked` entries are always part of the result, in order, and
+/// count towards `count`.  The remaining entries are fit to the input colors not already matched
+/// by a locked entry.
+pub fn generate(
+    method: Method,
+    name: &str,
+    colors: &[Color],
+    count: usize,
+    locked: &[Color],
+) -> Palette {
+    let locked_lab: Vec<glam::Vec3> = locked.iter().map(|c| oklab::from_srgb8(*c)).collect();
+    let samples = histogram(colors);
+    let free_count = count.saturating_sub(locked.len());
+
+    let free = match method {
+        Method::MedianCut => median_cut(&samples, &locked_lab, free_count),
+        Method::KMeans => {
+            let seeds = median_cut(&samples, &locked_lab, free_count);
+            kmeans(&samples, &locked_lab, seeds)
+        }
+    };
+
+    let mut palette_colors = locked.to_vec();
+    for lab in free {
+        let c = oklab::to_srgb8(lab);
+        if !palette_colors.contains(&c) {
+            palette_colors.push(c);
+        }
+    }
+    Palette::new(name, palette_colors)
+}
+
+/// Collapse duplicate colors into weighted OKLab samples.  Ordered so that generation is
+/// deterministic.
+fn histogram(colors: &[Color]) -> Vec<Sample> {
+    let mut counts = std::collections::BTreeMap::<Color, u32>::new();
+    for c in colors {
+        *counts.entry(*c).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(c, n)| Sample {
+            lab: oklab::from_srgb8(c),
+            weight: n as f32,
+        })
+        .collect()
+}
+
+fn is_locked(lab: glam::Vec3, locked: &[glam::Vec3]) -> bool {
+    locked
+        .iter()
+        .any(|l| l.distance_squared(lab) < LOCKED_EPSILON_SQ)
+}
+
+struct ColorBox {
+    samples: Vec<Sample>,
+}
+
+impl ColorBox {
+    fn weight(&self) -> f32 {
+        self.samples.iter().map(|s| s.weight).sum()
+    }
+
+    fn mean(&self) -> glam::Vec3 {
+        let sum: glam::Vec3 = self.samples.iter().map(|s| s.lab * s.weight).sum();
+        sum / self.weight()
+    }
+
+    /// Axis with the largest extent and that extent
+    fn widest_axis(&self) -> (usize, f32) {
+        let mut min = glam::Vec3::MAX;
+        let mut max = glam::Vec3::MIN;
+        for s in &self.samples {
+            min = min.min(s.lab);
+            max = max.max(s.lab);
+        }
+        let extent = max - min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        (axis, extent[axis])
+    }
+
+    /// Split at the weighted median along the widest axis
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (axis, _) = self.widest_axis();
+        self.samples
+            .sort_by(|a, b| a.lab[axis].total_cmp(&b.lab[axis]));
+
+        let half = self.weight() * 0.5;
+        let mut acc = 0.0;
+        let mut split = 1;
+        for (idx, s) in self.samples.iter().enumerate() {
+            acc += s.weight;
+            if acc >= half {
+                split = idx + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, self.samples.len() - 1);
+
+        let upper = self.samples.split_off(split);
+        (self, ColorBox { samples: upper })
+    }
+}
+
+fn median_cut(samples: &[Sample], locked: &[glam::Vec3], count: usize) -> Vec<glam::Vec3> {
+    let free: Vec<Sample> = samples
+        .iter()
+        .filter(|s| !is_locked(s.lab, locked))
+        .copied()
+        .collect();
+    if free.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![ColorBox { samples: free }];
+    while boxes.len() < count {
+        // Split the box whose extent, scaled by the number of pixels it covers, is largest
+        let candidate = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.samples.len() > 1)
+            .map(|(idx, b)| (idx, b.widest_axis().1 * b.weight().sqrt()))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let Some((idx, _)) = candidate else {
+            break;
+        };
+        let (lower, upper) = boxes.swap_remove(idx).split();
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|b| b.mean()).collect()
+}
+
+/// Refine `centroids` with Lloyd iterations.  Locked colors take part in the assignment step but
+/// never move.
+fn kmeans(
+    samples: &[Sample],
+    locked: &[glam::Vec3],
+    mut centroids: Vec<glam::Vec3>,
+) -> Vec<glam::Vec3> {
+    if centroids.is_empty() {
+        return centroids;
+    }
+
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![glam::Vec3::ZERO; centroids.len()];
+        let mut weights = vec![0.0f32; centroids.len()];
+
+        for s in samples {
+            let nearest_free = centroids
+                .iter()
+                .map(|c| c.distance_squared(s.lab))
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap();
+            let nearest_locked = locked
+                .iter()
+                .map(|c| c.distance_squared(s.lab))
+                .min_by(|a, b| a.total_cmp(b))
+                .unwrap_or(f32::MAX);
+
+            if nearest_free.1 <= nearest_locked {
+                sums[nearest_free.0] += s.lab * s.weight;
+                weights[nearest_free.0] += s.weight;
+            }
+        }
+
+        let mut moved = 0.0f32;
+        for (idx, centroid) in centroids.iter_mut().enumerate() {
+            // Empty clusters keep their previous position
+            if weights[idx] > 0.0 {
+                let updated = sums[idx] / weights[idx];
+                moved = moved.max(updated.distance_squared(*centroid));
+                *centroid = updated;
+            }
+        }
+
+        if moved < 1.0e-8 {
+            break;
+        }
+    }
+
+    centroids
+}
@@ -0,0 +1,168 @@
+use crate::{Color, Error, Palette};
+
+fn parse_error(line: usize, message: impl Into<String>) -> Error {
+    Error::Parse {
+        line: line + 1,
+        message: message.into(),
+    }
+}
+
+/// Parse whitespace separated "R G B" decimal triple, ignoring anything after the third value
+fn parse_rgb_triple(line_idx: usize, line: &str) -> Result<Color, Error> {
+    let mut values = line.split_whitespace().map(|v| v.parse::<u8>());
+    let mut color = [0u8; 3];
+    for c in color.iter_mut() {
+        *c = values
+            .next()
+            .ok_or_else(|| parse_error(line_idx, "expected three color components"))?
+            .map_err(|e| parse_error(line_idx, e.to_string()))?;
+    }
+    Ok(color)
+}
+
+/// GIMP palette:
+///
+/// ```text
+/// GIMP Palette
+/// Name: Example
+/// Columns: 4
+/// # comment
+///   0   0   0 Black
+/// 255 255 255 White
+/// ```
+pub fn parse_gpl(text: &str) -> Result<Palette, Error> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == "GIMP Palette" => {}
+        _ => return Err(parse_error(0, "missing 'GIMP Palette' header")),
+    }
+
+    let mut palette = Palette::default();
+    for (line_idx, line) in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("Name:") {
+            palette.name = name.trim().to_string();
+            continue;
+        }
+        if line.starts_with("Columns:") {
+            continue;
+        }
+        palette.colors.push(parse_rgb_triple(line_idx, line)?);
+    }
+    Ok(palette)
+}
+
+/// Lospec hex list: one `RRGGBB` per line, optionally prefixed with `#`
+pub fn parse_hex(text: &str) -> Result<Palette, Error> {
+    let mut palette = Palette::default();
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let hex = line.strip_prefix('#').unwrap_or(line);
+        // from_str_radix alone would also accept a sign
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(parse_error(
+                line_idx,
+                format!("expected RRGGBB, found '{}'", line),
+            ));
+        }
+        let value = u32::from_str_radix(hex, 16)
+            .map_err(|e| parse_error(line_idx, format!("'{}': {}", line, e)))?;
+        palette
+            .colors
+            .push([(value >> 16) as u8, (value >> 8) as u8, value as u8]);
+    }
+    Ok(palette)
+}
+
+/// JASC (Paint Shop Pro) palette:
+///
+/// ```text
+/// JASC-PAL
+/// 0100
+/// 2
+/// 0 0 0
+/// 255 255 255
+/// ```
+pub fn parse_pal(text: &str) -> Result<Palette, Error> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == "JASC-PAL" => {}
+        _ => return Err(parse_error(0, "missing 'JASC-PAL' header")),
+    }
+    // Version, always 0100
+    lines.next();
+
+    let count = match lines.next() {
+        Some((line_idx, line)) => line
+            .trim()
+            .parse::<usize>()
+            .map_err(|e| parse_error(line_idx, format!("invalid color count: {}", e)))?,
+        None => return Err(parse_error(2, "missing color count")),
+    };
+
+    let mut palette = Palette::default();
+    for (line_idx, line) in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        palette.colors.push(parse_rgb_triple(line_idx, line)?);
+    }
+
+    if palette.colors.len() != count {
+        return Err(parse_error(
+            2,
+            format!(
+                "header declares {} colors but {} were found",
+                count,
+                palette.colors.len()
+            ),
+        ));
+    }
+    Ok(palette)
+}
+
+/// Swatch image: each pixel of the first row is a palette entry.  Transparent pixels and repeated
+/// colors are skipped.
+pub fn from_swatch(image: &image::RgbaImage) -> Palette {
+    let mut palette = Palette::default();
+    for x in 0..image.width() {
+        let [r, g, b, a] = image.get_pixel(x, 0).0;
+        if a == 0 || palette.colors.contains(&[r, g, b]) {
+            continue;
+        }
+        palette.colors.push([r, g, b]);
+    }
+    palette
+}
+
+pub fn write_gpl(palette: &Palette) -> String {
+    let mut text = String::from("GIMP Palette\n");
+    text += &format!("Name: {}\n", palette.name);
+    text += "#\n";
+    for [r, g, b] in &palette.colors {
+        text += &format!("{:3} {:3} {:3}\t#{:02x}{:02x}{:02x}\n", r, g, b, r, g, b);
+    }
+    text
+}
+
+pub fn write_hex(palette: &Palette) -> String {
+    let mut text = String::new();
+    for [r, g, b] in &palette.colors {
+        text += &format!("{:02x}{:02x}{:02x}\n", r, g, b);
+    }
+    text
+}
+
+pub fn write_pal(palette: &Palette) -> String {
+    let mut text = format!("JASC-PAL\r\n0100\r\n{}\r\n", palette.len());
+    for [r, g, b] in &palette.colors {
+        text += &format!("{} {} {}\r\n", r, g, b);
+    }
+    text
+}
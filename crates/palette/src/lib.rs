@@ -0,0 +1,161 @@
+pub mod extract;
+mod format;
+pub mod oklab;
+#[cfg(test)]
+mod tests;
+
+use std::path::Path;
+
+/// 8-bit sRGB color
+pub type Color = [u8; 3];
+
+/// Errors produced while reading or writing palette files
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    /// File contents did not match the expected format.  `line` is 1-based.
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// File extension is not a supported palette format
+    UnknownFormat(String),
+    /// File parsed correctly but contained no colors
+    Empty,
+    /// File has more than `Palette::MAX_COLORS` colors
+    TooManyColors(usize),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Image(e) => write!(f, "{}", e),
+            Error::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            Error::UnknownFormat(ext) => write!(f, "unknown palette format '{}'", ext),
+            Error::Empty => write!(f, "palette contains no colors"),
+            Error::TooManyColors(count) => write!(
+                f,
+                "palette has {} colors, at most {} are supported",
+                count,
+                Palette::MAX_COLORS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+/// Ordered list of colors used to quantize pixelated output.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub colors: Vec<Color>,
+}
+
+impl Palette {
+    /// Maximum number of colors the renderer can quantize to
+    pub const MAX_COLORS: usize = 256;
+
+    pub fn new(name: &str, colors: Vec<Color>) -> Self {
+        Self {
+            name: name.to_string(),
+            colors,
+        }
+    }
+
+    /// Load palette file, selecting the format from the file extension:
+    ///   * `.gpl` GIMP palette
+    ///   * `.hex` Lospec hex list
+    ///   * `.pal` JASC palette
+    ///   * `.png` swatch image, one color per pixel of the first row
+    ///
+    /// Files with more than `MAX_COLORS` colors are rejected.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let mut palette = match ext.as_str() {
+            "gpl" => format::parse_gpl(&std::fs::read_to_string(path)?)?,
+            "hex" => format::parse_hex(&std::fs::read_to_string(path)?)?,
+            "pal" => format::parse_pal(&std::fs::read_to_string(path)?)?,
+            "png" => format::from_swatch(&image::open(path)?.to_rgba8()),
+            _ => return Err(Error::UnknownFormat(ext)),
+        };
+
+        if palette.colors.is_empty() {
+            return Err(Error::Empty);
+        }
+        if palette.colors.len() > Self::MAX_COLORS {
+            return Err(Error::TooManyColors(palette.colors.len()));
+        }
+        if palette.name.is_empty() {
+            palette.name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+        Ok(palette)
+    }
+
+    /// Write palette file, selecting the format from the file extension.  Supports `.gpl`,
+    /// `.hex` and `.pal`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let text = match ext.as_str() {
+            "gpl" => format::write_gpl(self),
+            "hex" => format::write_hex(self),
+            "pal" => format::write_pal(self),
+            _ => return Err(Error::UnknownFormat(ext)),
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Palette colors converted to OKLab
+    pub fn to_oklab(&self) -> Vec<glam::Vec3> {
+        self.colors.iter().map(|c| oklab::from_srgb8(*c)).collect()
+    }
+
+    /// Index of the palette entry perceptually closest to `color` (OKLab distance)
+    pub fn nearest(&self, color: Color) -> Option<usize> {
+        let lab = oklab::from_srgb8(color);
+        self.colors
+            .iter()
+            .map(|c| oklab::from_srgb8(*c).distance_squared(lab))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(idx, _)| idx)
+    }
+}
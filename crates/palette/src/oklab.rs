@@ -0,0 +1,84 @@
+//! Conversions between sRGB, linear RGB and the OKLab perceptual color space.
+//!
+//! See <https://bottosson.github.io/posts/oklab/>.  The render crate's quantize shader carries a
+//! copy of these formulas; keep the two in sync.
+
+// Coefficients are kept as published so they match the shader copy digit for digit
+#![allow(clippy::excessive_precision)]
+
+/// sRGB transfer function, single channel in [0,1]
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Inverse sRGB transfer function, single channel in [0,1]
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn from_linear(c: glam::Vec3) -> glam::Vec3 {
+    let l = 0.4122214708 * c.x + 0.5363325363 * c.y + 0.0514459929 * c.z;
+    let m = 0.2119034982 * c.x + 0.6806995451 * c.y + 0.1073969566 * c.z;
+    let s = 0.0883024619 * c.x + 0.2817188376 * c.y + 0.6299787005 * c.z;
+
+    let l = l.cbrt();
+    let m = m.cbrt();
+    let s = s.cbrt();
+
+    glam::Vec3::new(
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    )
+}
+
+pub fn to_linear(lab: glam::Vec3) -> glam::Vec3 {
+    let l = lab.x + 0.3963377774 * lab.y + 0.2158037573 * lab.z;
+    let m = lab.x - 0.1055613458 * lab.y - 0.0638541728 * lab.z;
+    let s = lab.x - 0.0894841775 * lab.y - 1.2914855480 * lab.z;
+
+    let l = l * l * l;
+    let m = m * m * m;
+    let s = s * s * s;
+
+    glam::Vec3::new(
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    )
+}
+
+/// Linear RGB of an 8-bit sRGB color
+pub fn srgb8_to_linear(c: [u8; 3]) -> glam::Vec3 {
+    glam::Vec3::new(
+        srgb_to_linear(c[0] as f32 / 255.0),
+        srgb_to_linear(c[1] as f32 / 255.0),
+        srgb_to_linear(c[2] as f32 / 255.0),
+    )
+}
+
+/// 8-bit sRGB color of a linear RGB value, clamped to the sRGB gamut
+pub fn linear_to_srgb8(c: glam::Vec3) -> [u8; 3] {
+    let c = c.clamp(glam::Vec3::ZERO, glam::Vec3::ONE);
+    [
+        (linear_to_srgb(c.x) * 255.0).round() as u8,
+        (linear_to_srgb(c.y) * 255.0).round() as u8,
+        (linear_to_srgb(c.z) * 255.0).round() as u8,
+    ]
+}
+
+pub fn from_srgb8(c: [u8; 3]) -> glam::Vec3 {
+    from_linear(srgb8_to_linear(c))
+}
+
+pub fn to_srgb8(lab: glam::Vec3) -> [u8; 3] {
+    linear_to_srgb8(to_linear(lab))
+}
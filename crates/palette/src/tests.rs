@@ -0,0 +1,242 @@
+use crate::extract::{generate, Method};
+use crate::{format, Color, Error, Palette};
+
+/// 16x16 image sweeping red and green, 256 distinct colors
+fn gradient() -> Vec<Color> {
+    (0..256u32)
+        .map(|i| [(i % 16 * 17) as u8, (i / 16 * 17) as u8, 96])
+        .collect()
+}
+
+/// `counts[i]` pixels of each of a few well separated colors
+fn clusters(counts: &[usize]) -> Vec<Color> {
+    const COLORS: [Color; 4] = [[0, 0, 0], [255, 255, 255], [200, 30, 30], [30, 60, 220]];
+    counts
+        .iter()
+        .zip(COLORS)
+        .flat_map(|(&n, c)| std::iter::repeat(c).take(n))
+        .collect()
+}
+
+#[test]
+fn generates_requested_count() {
+    for method in Method::ALL {
+        for count in [1, 2, 5, 16] {
+            let palette = generate(method, "p", &gradient(), count, &[]);
+            assert_eq!(palette.len(), count, "{:?} count {}", method, count);
+        }
+    }
+}
+
+#[test]
+fn fewer_distinct_colors_than_requested() {
+    let colors = clusters(&[10, 20, 5, 1]);
+    for method in Method::ALL {
+        let mut palette = generate(method, "p", &colors, 16, &[]);
+        palette.colors.sort();
+        let mut expected = vec![[0, 0, 0], [30, 60, 220], [200, 30, 30], [255, 255, 255]];
+        expected.sort();
+        assert_eq!(palette.colors, expected, "{:?}", method);
+    }
+}
+
+#[test]
+fn keeps_locked_colors_first() {
+    let locked = [[255, 0, 255], [0, 0, 0]];
+    for method in Method::ALL {
+        let palette = generate(method, "p", &gradient(), 8, &locked);
+        assert_eq!(palette.len(), 8, "{:?}", method);
+        assert_eq!(&palette.colors[..2], &locked, "{:?}", method);
+        // Nothing else duplicates a locked entry
+        assert!(!palette.colors[2..].contains(&locked[0]));
+        assert!(!palette.colors[2..].contains(&locked[1]));
+    }
+}
+
+#[test]
+fn locked_colors_fill_the_palette() {
+    let locked = [[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+    for method in Method::ALL {
+        let palette = generate(method, "p", &gradient(), 3, &locked);
+        assert_eq!(palette.colors, locked, "{:?}", method);
+    }
+}
+
+#[test]
+fn empty_image() {
+    for method in Method::ALL {
+        assert!(generate(method, "p", &[], 8, &[]).is_empty());
+        let locked = [[10, 20, 30]];
+        assert_eq!(generate(method, "p", &[], 8, &locked).colors, locked);
+    }
+}
+
+#[test]
+fn single_color_image() {
+    let colors = vec![[12, 200, 99]; 64];
+    for method in Method::ALL {
+        let palette = generate(method, "p", &colors, 8, &[]);
+        assert_eq!(palette.colors, vec![[12, 200, 99]], "{:?}", method);
+    }
+}
+
+#[test]
+fn deterministic() {
+    let colors = gradient();
+    let mut reversed = colors.clone();
+    reversed.reverse();
+    for method in Method::ALL {
+        let first = generate(method, "p", &colors, 12, &[[0, 0, 0]]);
+        let second = generate(method, "p", &colors, 12, &[[0, 0, 0]]);
+        assert_eq!(first, second, "{:?}", method);
+        // Pixel order doesn't matter, only how often each color occurs
+        let third = generate(method, "p", &reversed, 12, &[[0, 0, 0]]);
+        assert_eq!(first, third, "{:?}", method);
+    }
+}
+
+/// Line number of a parse error
+fn error_line(result: Result<Palette, Error>) -> usize {
+    match result {
+        Err(Error::Parse { line, .. }) => line,
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn parses_gpl() {
+    let text = "GIMP Palette\n\
+                Name: Example Palette\n\
+                Columns: 4\n\
+                # comment\n\
+                \n\
+                \x20 0   0   0\tBlack\n\
+                255 128  12 Orange\n";
+    let palette = format::parse_gpl(text).unwrap();
+    assert_eq!(palette.name, "Example Palette");
+    assert_eq!(palette.colors, vec![[0, 0, 0], [255, 128, 12]]);
+}
+
+#[test]
+fn rejects_malformed_gpl() {
+    assert_eq!(error_line(format::parse_gpl("0 0 0\n")), 1);
+    assert_eq!(
+        error_line(format::parse_gpl("GIMP Palette\n0 0 0\n1 2\n")),
+        3
+    );
+    assert_eq!(error_line(format::parse_gpl("GIMP Palette\n0 0 256\n")), 2);
+    assert_eq!(
+        error_line(format::parse_gpl("GIMP Palette\nred green blue\n")),
+        2
+    );
+}
+
+#[test]
+fn parses_hex() {
+    let palette = format::parse_hex("000000\n#FF8000\n\n  1a2B3c  \r\n").unwrap();
+    assert_eq!(palette.colors, vec![[0, 0, 0], [255, 128, 0], [26, 43, 60]]);
+}
+
+#[test]
+fn rejects_malformed_hex() {
+    assert_eq!(error_line(format::parse_hex("000000\nfff\n")), 2);
+    assert_eq!(error_line(format::parse_hex("00000g\n")), 1);
+    assert_eq!(error_line(format::parse_hex("0000000\n")), 1);
+    // Accepted by u32::from_str_radix
+    assert_eq!(error_line(format::parse_hex("+12345\n")), 1);
+    assert_eq!(error_line(format::parse_hex("#-12345\n")), 1);
+}
+
+#[test]
+fn parses_pal() {
+    let text = "JASC-PAL\r\n0100\r\n3\r\n0 0 0\r\n255 255 255\r\n\r\n10 20 30\r\n";
+    let palette = format::parse_pal(text).unwrap();
+    assert_eq!(
+        palette.colors,
+        vec![[0, 0, 0], [255, 255, 255], [10, 20, 30]]
+    );
+}
+
+#[test]
+fn rejects_malformed_pal() {
+    assert_eq!(error_line(format::parse_pal("GIMP Palette\n")), 1);
+    assert_eq!(error_line(format::parse_pal("JASC-PAL\n0100\n")), 3);
+    assert_eq!(error_line(format::parse_pal("JASC-PAL\n0100\ntwo\n")), 3);
+    // Count doesn't match the colors
+    assert_eq!(
+        error_line(format::parse_pal("JASC-PAL\n0100\n2\n0 0 0\n")),
+        3
+    );
+    assert_eq!(error_line(format::parse_pal("JASC-PAL\n0100\n1\n0 0\n")), 4);
+}
+
+#[test]
+fn reads_swatch() {
+    // Transparent and repeated pixels are skipped, only the first row is read
+    let mut image = image::RgbaImage::new(5, 2);
+    let row = [
+        [10, 20, 30, 255],
+        [0, 0, 0, 0],
+        [40, 50, 60, 128],
+        [10, 20, 30, 255],
+        [70, 80, 90, 255],
+    ];
+    for (x, p) in row.into_iter().enumerate() {
+        image.put_pixel(x as u32, 0, image::Rgba(p));
+        image.put_pixel(x as u32, 1, image::Rgba([1, 2, 3, 255]));
+    }
+    let palette = format::from_swatch(&image);
+    assert_eq!(
+        palette.colors,
+        vec![[10, 20, 30], [40, 50, 60], [70, 80, 90]]
+    );
+}
+
+#[test]
+fn writers_round_trip() {
+    let palette = Palette::new("Round Trip", vec![[0, 0, 0], [255, 128, 12], [1, 2, 3]]);
+    assert_eq!(
+        format::parse_gpl(&format::write_gpl(&palette)).unwrap(),
+        palette
+    );
+    assert_eq!(
+        format::parse_hex(&format::write_hex(&palette))
+            .unwrap()
+            .colors,
+        palette.colors
+    );
+    assert_eq!(
+        format::parse_pal(&format::write_pal(&palette))
+            .unwrap()
+            .colors,
+        palette.colors
+    );
+}
+
+#[test]
+fn load_checks_color_count() {
+    let dir = std::env::temp_dir().join(format!("palette-tests-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("named_after_file.hex");
+    std::fs::write(&path, "000000\nffffff\n").unwrap();
+    let palette = Palette::load(&path).unwrap();
+    assert_eq!(palette.name, "named_after_file");
+    assert_eq!(palette.len(), 2);
+
+    let path = dir.join("empty.hex");
+    std::fs::write(&path, "\n").unwrap();
+    assert!(matches!(Palette::load(&path), Err(Error::Empty)));
+
+    let path = dir.join("large.hex");
+    let colors: Vec<Color> = (0..300u32).map(|i| [0, (i >> 8) as u8, i as u8]).collect();
+    std::fs::write(&path, format::write_hex(&Palette::new("large", colors))).unwrap();
+    assert!(matches!(
+        Palette::load(&path),
+        Err(Error::TooManyColors(300))
+    ));
+
+    let path = dir.join("palette.txt");
+    std::fs::write(&path, "000000\n").unwrap();
+    assert!(matches!(Palette::load(&path), Err(Error::UnknownFormat(_))));
+}
@@ -13,6 +13,30 @@ struct Light {
 
 };
 
+struct Material {
+    vec4 base_color;
+
+    vec3 emissive;
+    float metallic;
+
+    float roughness;
+    float normal_scale;
+    float occlusion_strength;
+    float alpha_cutoff;
+};
+
+#ifdef SHADING_CEL
+struct Cel {
+    uint bands;
+    float specular_threshold;
+    float specular_shininess;
+    float specular_intensity;
+
+    // Up to 7 ascending N.L band thresholds, packed 4 per vec4
+    vec4 thresholds[2];
+};
+#endif // SHADING_CEL
+
 // inputs
 layout(location = 0) in vec3 world_pos;
 layout(location = 1) in vec3 object_pos;
@@ -20,32 +44,251 @@ layout(location = 1) in vec3 object_pos;
 layout(location = 2) in vec3 object_normal;
 #endif
 layout(location = 3) in vec2 uv;
+layout(location = 4) in float view_depth;
+#ifdef HAS_TANGENTS
+layout(location = 5) in vec4 object_tangent;
+#endif // HAS_TANGENTS
+#ifdef HAS_COLORS
+layout(location = 6) in vec4 vertex_color;
+#endif // HAS_COLORS
 
 // outputs
-out vec4 color;
+layout(location = 0) out vec4 color;
+// Edge detection inputs, see outline.glsl.  View space normal and linear view depth, zero depth
+// marks the background.
+layout(location = 1) out vec4 normal_depth;
+// Mesh and material ids, see `SpriteMap`
+layout(location = 2) out uvec2 id;
+// Unlit base color for the albedo companion map
+layout(location = 3) out vec4 albedo;
 
 // uniforms
 layout (set = 1, binding = 1)
 uniform mat4 normal_transform;
 
+// Non-zero per-mesh and per-material ids
+layout (set = 1, binding = 2)
+uniform Ids {
+    uint object_id;
+    uint material_id;
+} ids;
+
 layout (set = 2, binding = 0)
 uniform Light light;
 
+#ifdef SHADING_CEL
+layout (set = 2, binding = 1)
+uniform Cel cel;
+
+#ifdef USE_RAMP
+layout (set = 2, binding = 2)
+uniform texture2D ramp_texture;
+
+layout (set = 2, binding = 3)
+uniform sampler ramp_sampler;
+#endif // USE_RAMP
+#endif // SHADING_CEL
+
+layout (set = 3, binding = 0)
+uniform Material material;
+
+// Materials without a texture get one that leaves the factor unchanged
+layout (set = 3, binding = 1)
+uniform texture2D base_color_texture;
+
+layout (set = 3, binding = 2)
+uniform sampler base_color_sampler;
+
+#ifdef SHADING_PBR
+// Roughness in G, metalness in B
+layout (set = 3, binding = 3)
+uniform texture2D metallic_roughness_texture;
+
+layout (set = 3, binding = 4)
+uniform sampler metallic_roughness_sampler;
+
+layout (set = 3, binding = 5)
+uniform texture2D normal_texture;
+
+layout (set = 3, binding = 6)
+uniform sampler normal_sampler;
+
+layout (set = 3, binding = 7)
+uniform texture2D occlusion_texture;
+
+layout (set = 3, binding = 8)
+uniform sampler occlusion_sampler;
+
+layout (set = 3, binding = 9)
+uniform texture2D emissive_texture;
+
+layout (set = 3, binding = 10)
+uniform sampler emissive_sampler;
+
+const float PI = 3.14159265359;
+
+// Apply the normal map to the object space normal n.  Meshes without tangents get a tangent
+// frame built from screen space derivatives of the position and texcoords.
+vec3 perturb_normal(vec3 n)
+{
+#ifdef HAS_TEXCOORDS
+    vec3 tn = texture(sampler2D(normal_texture, normal_sampler), uv).xyz * 2.0 - 1.0;
+    tn.xy *= material.normal_scale;
+
+#ifdef HAS_TANGENTS
+    vec3 t = object_tangent.xyz - n * dot(n, object_tangent.xyz);
+    if (dot(t, t) < 1e-12) {
+        return n;
+    }
+    t = normalize(t);
+    vec3 b = cross(n, t) * (object_tangent.w < 0.0 ? -1.0 : 1.0);
+    return normalize(mat3(t, b, n) * tn);
+#else
+    vec2 duv1 = dFdx(uv);
+    vec2 duv2 = dFdy(uv);
+    float det = duv1.x * duv2.y - duv2.x * duv1.y;
+    vec3 t = (duv2.y * dFdx(object_pos) - duv1.y * dFdy(object_pos)) / det;
+    t -= n * dot(n, t);
+    if (abs(det) < 1e-12 || dot(t, t) < 1e-12) {
+        return n;
+    }
+    t = normalize(t);
+    vec3 b = cross(n, t);
+    return normalize(mat3(t, b, n) * tn);
+#endif // HAS_TANGENTS
+#else
+    return n;
+#endif // HAS_TEXCOORDS
+}
+
+// Approximate split-sum environment BRDF (Karis, "Physically Based Shading on Mobile")
+vec3 env_brdf(vec3 f0, float roughness, float n_dot_v)
+{
+    const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+    const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+    vec4 r = roughness * c0 + c1;
+    float a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+    vec2 ab = vec2(-1.04, 1.04) * a004 + r.zw;
+    return f0 * ab.x + ab.y;
+}
+
+// Metallic-roughness BRDF: Lambert diffuse plus GGX specular with a height-correlated Smith
+// visibility term and Schlick Fresnel.  Ambient light is treated as uniform irradiance.
+vec3 shade_pbr(vec3 n, vec3 w_in, vec3 base_color)
+{
+    float roughness = material.roughness;
+    float metallic = material.metallic;
+    float occlusion = 1.0;
+    vec3 emissive = material.emissive;
+#ifdef HAS_TEXCOORDS
+    vec4 mr = texture(sampler2D(metallic_roughness_texture, metallic_roughness_sampler), uv);
+    roughness *= mr.g;
+    metallic *= mr.b;
+    float ao = texture(sampler2D(occlusion_texture, occlusion_sampler), uv).r;
+    occlusion = 1.0 + material.occlusion_strength * (ao - 1.0);
+    emissive *= texture(sampler2D(emissive_texture, emissive_sampler), uv).rgb;
+#endif // HAS_TEXCOORDS
+    roughness = clamp(roughness, 0.04, 1.0);
+    metallic = clamp(metallic, 0.0, 1.0);
+
+    vec3 diffuse_color = base_color * (1.0 - metallic);
+    vec3 f0 = mix(vec3(0.04), base_color, metallic);
+
+    // Normals are in view space, approximate the view direction by +Z
+    vec3 v = vec3(0.0, 0.0, 1.0);
+    vec3 h = normalize(w_in + v);
+    float n_dot_l = max(0.0, dot(n, w_in));
+    float n_dot_v = max(1e-4, dot(n, v));
+    float n_dot_h = max(0.0, dot(n, h));
+    float v_dot_h = max(0.0, dot(v, h));
+
+    float a2 = roughness * roughness * roughness * roughness;
+    float d_denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    float d = a2 / (PI * d_denom * d_denom);
+    float vis_denom = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - a2) + a2)
+        + n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - a2) + a2);
+    float vis = vis_denom > 0.0 ? 0.5 / vis_denom : 0.0;
+    vec3 f = f0 + (1.0 - f0) * pow(1.0 - v_dot_h, 5.0);
+
+    // Light intensity is scaled by PI to match the brightness of the Lambert model
+    vec3 direct = ((1.0 - f) * diffuse_color + PI * f * d * vis)
+        * n_dot_l * light.color * light.scale;
+    vec3 ambient = (diffuse_color + env_brdf(f0, roughness, n_dot_v))
+        * light.ambient * light.ambient_scale * occlusion;
+    return direct + ambient + emissive;
+}
+#endif // SHADING_PBR
+
 void main()
 {
 
-#ifndef HAS_NORMALS
+#ifdef HAS_NORMALS
+    // Back faces, visible on double-sided surfaces, are lit from the other side
+    vec3 surface_normal = gl_FrontFacing ? object_normal : -object_normal;
+#else
     vec3 dx = dFdx(object_pos);
     vec3 dy = dFdy(object_pos);
-    vec3 object_normal = normalize(cross(dy, dx));
+    vec3 surface_normal = normalize(cross(dy, dx));
 #endif
-    vec3 world_normal = normalize(mat3(normal_transform)*object_normal);
+    vec3 world_normal = normalize(mat3(normal_transform)*surface_normal);
 
     vec3 w_in = -normalize(light.dir);
     float n_dot_w = max(0.0, dot(world_normal, w_in));
+#ifdef SHADING_CEL
+    // Index of the band N.L falls in
+    uint band = 0u;
+    for (uint i = 0u; i + 1u < cel.bands; ++i) {
+        if (n_dot_w > cel.thresholds[i / 4u][i % 4u]) {
+            band = i + 1u;
+        }
+    }
+#ifdef USE_RAMP
+    vec2 ramp_uv = vec2((float(band) + 0.5) / float(cel.bands), 0.5);
+    vec3 diffuse = texture(sampler2D(ramp_texture, ramp_sampler), ramp_uv).rgb;
+#else
+    vec3 diffuse = vec3(float(band) / float(cel.bands - 1u));
+#endif // USE_RAMP
+    diffuse *= light.color * light.scale;
+
+    // Normals are in view space, approximate the view direction by +Z
+    vec3 h = normalize(w_in + vec3(0.0, 0.0, 1.0));
+    float n_dot_h = max(0.0, dot(world_normal, h));
+    float spec = pow(n_dot_h, cel.specular_shininess);
+    vec3 specular = spec > cel.specular_threshold
+        ? cel.specular_intensity * light.color
+        : vec3(0.0);
+
+    vec3 l = diffuse + specular + light.ambient*light.ambient_scale;
+#else
     vec3 l = n_dot_w * light.color * light.scale + light.ambient*light.ambient_scale;
-    vec3 b = vec3(1.0); 
-    color = vec4(b*l, 1.0);
+#endif // SHADING_CEL
+    vec4 b = material.base_color;
+#ifdef HAS_TEXCOORDS
+    b *= texture(sampler2D(base_color_texture, base_color_sampler), uv);
+#endif // HAS_TEXCOORDS
+#ifdef HAS_COLORS
+    b *= vertex_color;
+#endif // HAS_COLORS
+#ifdef SHADING_PBR
+    vec3 shading_normal = normalize(mat3(normal_transform)*perturb_normal(normalize(surface_normal)));
+    vec3 shaded = shade_pbr(shading_normal, w_in, b.rgb);
+#else
+    vec3 shaded = b.rgb*l;
+#endif // SHADING_PBR
+
+    // The color target holds premultiplied alpha
+#if defined(ALPHA_MASK)
+    if (b.a < material.alpha_cutoff) {
+        discard;
+    }
+    color = vec4(shaded, 1.0);
+#elif defined(ALPHA_BLEND)
+    color = vec4(shaded * b.a, b.a);
+#else
+    color = vec4(shaded, 1.0);
+#endif
 
-    //color = vec4((object_normal+vec3(1.0))*0.5, 1.0f);
+    normal_depth = vec4(world_normal, view_depth);
+    id = uvec2(ids.object_id, ids.material_id);
+    albedo = vec4(b.rgb, color.a);
 }
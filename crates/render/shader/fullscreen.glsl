@@ -0,0 +1,12 @@
+#version 450
+
+// Single triangle covering the whole viewport.  Used by the image-space passes, which address
+// their inputs with gl_FragCoord rather than interpolated texture coordinates.
+
+// outputs
+layout (location = 0) out vec2 uv;
+
+void main() {
+    uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,26 @@
+#version 450
+
+// Flat line color for the inverted-hull outline, see hull_vert.glsl
+
+// outputs
+layout (location = 0) out vec4 color;
+// Hull pixels count as background for screen-space edge detection
+layout (location = 1) out vec4 normal_depth;
+layout (location = 2) out uvec2 id;
+layout (location = 3) out vec4 albedo;
+
+// uniforms
+layout (set = 2, binding = 0)
+uniform Hull {
+    vec4 line_color;
+    vec2 pixel_size;
+    float thickness;
+} hull;
+
+void main()
+{
+    color = vec4(hull.line_color.rgb, 1.0);
+    normal_depth = vec4(0.0);
+    id = uvec2(0u);
+    albedo = vec4(0.0);
+}
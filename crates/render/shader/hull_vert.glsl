@@ -0,0 +1,87 @@
+#version 450
+
+// Inverted-hull outline geometry.  Each vertex is pushed out along its normal in screen space by
+// a fixed number of output (pixel-art) pixels, and the hull is drawn with front faces culled so
+// only the rim around the mesh remains visible.
+
+// inputs
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal;
+#ifdef HAS_JOINTS
+layout (location = 2) in uvec4 joints;
+layout (location = 3) in vec4 weights;
+#endif // HAS_JOINTS
+
+// uniforms
+layout (set = 0, binding = 0)
+uniform mat4 view_proj_transform;
+
+layout (set = 1, binding = 0)
+uniform mat4 model_transform;
+
+layout (set = 2, binding = 0)
+uniform Hull {
+    // Linear RGB line color, w unused
+    vec4 line_color;
+    // Pixel-art output resolution
+    vec2 pixel_size;
+    // Line thickness in output pixels
+    float thickness;
+} hull;
+
+#ifdef HAS_JOINTS
+layout (set = 1, binding = 3)
+readonly buffer Joints {
+    mat4 joint_matrices[];
+};
+#endif // HAS_JOINTS
+
+#ifdef HAS_MORPH_TARGETS
+// Position, normal and tangent deltas, indexed [target][attribute][vertex]
+layout (set = 1, binding = 4)
+readonly buffer MorphDeltas {
+    vec4 morph_deltas[];
+};
+
+layout (set = 1, binding = 5)
+readonly buffer MorphWeights {
+    uint morph_target_count;
+    uint morph_vertex_count;
+    float morph_weights[];
+};
+#endif // HAS_MORPH_TARGETS
+
+void main() {
+#ifdef HAS_JOINTS
+    mat4 skin_transform =
+        weights.x * joint_matrices[joints.x] +
+        weights.y * joint_matrices[joints.y] +
+        weights.z * joint_matrices[joints.z] +
+        weights.w * joint_matrices[joints.w];
+    mat4 transform = model_transform * skin_transform;
+#else
+    mat4 transform = model_transform;
+#endif // HAS_JOINTS
+
+    vec3 morphed_pos = pos;
+    vec3 morphed_normal = normal;
+#ifdef HAS_MORPH_TARGETS
+    for (uint t = 0u; t < morph_target_count; ++t) {
+        uint delta_idx = 3u * t * morph_vertex_count + uint(gl_VertexIndex);
+        morphed_pos += morph_weights[t] * morph_deltas[delta_idx].xyz;
+        morphed_normal += morph_weights[t] * morph_deltas[delta_idx + morph_vertex_count].xyz;
+    }
+#endif // HAS_MORPH_TARGETS
+
+    vec4 clip = view_proj_transform * transform * vec4(morphed_pos, 1.0);
+    vec4 clip_normal = view_proj_transform * vec4(mat3(transform) * morphed_normal, 0.0);
+
+    // Extrusion direction measured in output pixels so lines have the same width on both axes
+    vec2 dir = clip_normal.xy * hull.pixel_size;
+    if (dot(dir, dir) > 0.0) {
+        dir = normalize(dir);
+    }
+    vec2 offset_ndc = dir * hull.thickness * 2.0 / hull.pixel_size;
+
+    gl_Position = vec4(clip.xy + offset_ndc * clip.w, clip.zw);
+}
@@ -0,0 +1,97 @@
+#version 450
+
+// Companion maps of the pixel-art sprite for 2D lighting and masking.  Each output pixel reads
+// the full resolution buffers at the source texel the pixelizer picked for it, so the maps line
+// up with the color sprite.  Pixels the sprite leaves transparent stay transparent, sprite
+// pixels without geometry underneath (outlines) get a flat facing normal, far depth, their own
+// color as albedo and id 0.
+
+#define MAP_NORMAL 0u
+#define MAP_DEPTH 1u
+#define MAP_ALBEDO 2u
+#define MAP_MESH_ID 3u
+#define MAP_MATERIAL_ID 4u
+
+// inputs
+layout (location = 0) in vec2 uv;
+
+// outputs
+layout (location = 0) out vec4 color;
+
+// uniforms
+layout (set = 0, binding = 0)
+uniform utexture2D source_texel_texture;
+
+layout (set = 0, binding = 1)
+uniform sampler src_sampler;
+
+layout (set = 0, binding = 2)
+uniform Params {
+    uint map;
+    // View depths mapped to white and black in the depth map
+    float depth_near;
+    float depth_far;
+} params;
+
+// Final color sprite
+layout (set = 0, binding = 3)
+uniform texture2D sprite_texture;
+
+layout (set = 0, binding = 4)
+uniform texture2D normal_depth_texture;
+
+layout (set = 0, binding = 5)
+uniform utexture2D id_texture;
+
+layout (set = 0, binding = 6)
+uniform texture2D albedo_texture;
+
+vec3 linear_to_srgb(vec3 c) {
+    vec3 lo = c * 12.92;
+    vec3 hi = 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055;
+    return mix(hi, lo, lessThan(c, vec3(0.0031308)));
+}
+
+// Distinct flat color per id, hues spaced by the golden angle.  Id 0 is black.
+vec3 id_color(uint id) {
+    if (id == 0u) {
+        return vec3(0.0);
+    }
+    float hue = fract(float(id) * 0.618034);
+    vec3 rgb = clamp(abs(fract(hue + vec3(0.0, 2.0, 1.0) / 3.0) * 6.0 - 3.0) - 1.0, 0.0, 1.0);
+    // Alternate brightness so neighbouring hues stay apart
+    return rgb * ((id & 1u) == 1u ? 1.0 : 0.6);
+}
+
+void main()
+{
+    ivec2 p = ivec2(gl_FragCoord.xy);
+    vec4 sprite = texelFetch(sampler2D(sprite_texture, src_sampler), p, 0);
+    if (sprite.a == 0.0) {
+        color = vec4(0.0);
+        return;
+    }
+
+    ivec2 src = ivec2(texelFetch(usampler2D(source_texel_texture, src_sampler), p, 0).xy);
+    uvec2 ids = texelFetch(usampler2D(id_texture, src_sampler), src, 0).xy;
+    bool geometry = ids.x != 0u;
+    vec4 nd = texelFetch(sampler2D(normal_depth_texture, src_sampler), src, 0);
+
+    if (params.map == MAP_NORMAL) {
+        vec3 n = geometry ? normalize(nd.xyz) : vec3(0.0, 0.0, 1.0);
+        color = vec4(n * 0.5 + 0.5, 1.0);
+    } else if (params.map == MAP_DEPTH) {
+        float range = max(params.depth_far - params.depth_near, 1e-6);
+        float d = geometry ? clamp((params.depth_far - nd.w) / range, 0.0, 1.0) : 0.0;
+        color = vec4(vec3(d), 1.0);
+    } else if (params.map == MAP_ALBEDO) {
+        vec3 albedo = geometry
+            ? linear_to_srgb(texelFetch(sampler2D(albedo_texture, src_sampler), src, 0).rgb)
+            : linear_to_srgb(sprite.rgb);
+        color = vec4(albedo, 1.0);
+    } else if (params.map == MAP_MESH_ID) {
+        color = vec4(id_color(ids.x), 1.0);
+    } else {
+        color = vec4(id_color(ids.y), 1.0);
+    }
+}
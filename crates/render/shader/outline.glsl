@@ -0,0 +1,121 @@
+#version 450
+
+// Screen-space outlines on the pixelated image.  Edges are found in the full resolution normal,
+// depth and object-id buffers, sampled at the center of each output pixel's block, so line
+// thickness is measured in output pixels.
+//
+// Lines are drawn on the far side of an edge: outer lines on the background pixels around the
+// silhouette, inner lines on the surface behind a depth, object or crease edge.  With no edges
+// enabled the input is passed through unchanged.
+
+#define EDGES_NONE 0u
+#define EDGES_OUTER 1u
+#define EDGES_ALL 2u
+
+// inputs
+layout (location = 0) in vec2 uv;
+
+// outputs
+layout (location = 0) out vec4 color;
+
+// uniforms
+layout (set = 0, binding = 0)
+uniform texture2D src_texture;
+
+layout (set = 0, binding = 1)
+uniform sampler src_sampler;
+
+layout (set = 0, binding = 2)
+uniform Params {
+    // Linear RGB line color, w unused
+    vec4 line_color;
+    uvec2 src_size;
+    uvec2 dst_size;
+    uint edges;
+    // Line thickness in output pixels
+    int thickness;
+    // Lines use the base color scaled by `darken` instead of `line_color` when non-zero
+    float darken;
+    // Relative depth difference treated as an edge
+    float depth_threshold;
+    // Cosine of the crease angle
+    float normal_threshold;
+} params;
+
+layout (set = 1, binding = 0)
+uniform texture2D normal_depth_texture;
+
+layout (set = 1, binding = 1)
+uniform utexture2D id_texture;
+
+struct Sample {
+    vec3 normal;
+    float depth;
+    uint id;
+};
+
+// G-buffer values at the center of output pixel `p`'s block, matching the nearest pixel filter
+Sample fetch_sample(ivec2 p) {
+    ivec2 src = (p * ivec2(params.src_size) + ivec2(params.src_size) / 2) / ivec2(params.dst_size);
+    vec4 nd = texelFetch(sampler2D(normal_depth_texture, src_sampler), src, 0);
+    uint id = texelFetch(usampler2D(id_texture, src_sampler), src, 0).r;
+    return Sample(nd.xyz, nd.w, id);
+}
+
+// Does neighbour `q` put a line on `p`
+bool is_edge(Sample p, Sample q) {
+    if (q.id == 0u) {
+        return false;
+    }
+    if (p.id == 0u) {
+        return true;
+    }
+    if (params.edges != EDGES_ALL || p.depth <= q.depth) {
+        return false;
+    }
+    return p.id != q.id
+        || p.depth - q.depth > params.depth_threshold * q.depth
+        || dot(p.normal, q.normal) < params.normal_threshold;
+}
+
+void main()
+{
+    ivec2 p = ivec2(gl_FragCoord.xy);
+    color = texelFetch(sampler2D(src_texture, src_sampler), p, 0);
+    if (params.edges == EDGES_NONE) {
+        return;
+    }
+
+    Sample center = fetch_sample(p);
+    ivec2 size = ivec2(params.dst_size);
+
+    // Closest neighbour within `thickness` (manhattan distance) that forms an edge with p
+    int best_dist = params.thickness + 1;
+    ivec2 best = p;
+    for (int dy = -params.thickness; dy <= params.thickness; ++dy) {
+        for (int dx = -params.thickness; dx <= params.thickness; ++dx) {
+            int dist = abs(dx) + abs(dy);
+            ivec2 q = p + ivec2(dx, dy);
+            if (dist == 0 || dist >= best_dist
+                || q.x < 0 || q.y < 0 || q.x >= size.x || q.y >= size.y) {
+                continue;
+            }
+            if (is_edge(center, fetch_sample(q))) {
+                best_dist = dist;
+                best = q;
+            }
+        }
+    }
+    if (best_dist > params.thickness) {
+        return;
+    }
+
+    if (params.darken > 0.0) {
+        // Outer lines take the color of the object they surround
+        ivec2 base = center.id == 0u ? best : p;
+        vec3 base_color = texelFetch(sampler2D(src_texture, src_sampler), base, 0).rgb;
+        color = vec4(base_color * params.darken, 1.0);
+    } else {
+        color = vec4(params.line_color.rgb, 1.0);
+    }
+}
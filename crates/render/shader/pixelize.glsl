@@ -0,0 +1,193 @@
+#version 450
+
+// Downsamples the full resolution render to the pixel-art resolution.  Each output pixel
+// covers a block of source texels which is reduced according to `params.filter_mode`.  The
+// source holds premultiplied alpha, the output straight alpha that is either 0 or 1.
+//
+// Alongside the color each output pixel records the source texel closest to its filtered
+// color.  Companion maps (normals, depth, ids) are sampled there, since averaging them the way
+// colors are would not give meaningful values.
+
+#define FILTER_NEAREST 0u
+#define FILTER_BOX 1u
+#define FILTER_MAJORITY 2u
+#define FILTER_EDGE_PRESERVING 3u
+
+// Majority filter compares at most MAX_MAJORITY_SAMPLES^2 texels per block
+#define MAX_MAJORITY_SAMPLES 16
+
+// inputs
+layout (location = 0) in vec2 uv;
+
+// outputs
+layout (location = 0) out vec4 color;
+layout (location = 1) out uvec2 source_texel;
+
+// uniforms
+layout (set = 0, binding = 0)
+uniform texture2D src_texture;
+
+layout (set = 0, binding = 1)
+uniform sampler src_sampler;
+
+layout (set = 0, binding = 2)
+uniform Params {
+    uvec2 src_size;
+    uvec2 dst_size;
+    uint filter_mode;
+    // Minimum block coverage for an output pixel to be opaque
+    float alpha_threshold;
+} params;
+
+vec4 fetch(ivec2 p) {
+    return texelFetch(sampler2D(src_texture, src_sampler), p, 0);
+}
+
+float luminance(vec3 c) {
+    return dot(c, vec3(0.2126, 0.7152, 0.0722));
+}
+
+vec4 filter_nearest(ivec2 block_min, ivec2 block_max) {
+    return fetch((block_min + block_max) / 2);
+}
+
+vec4 filter_box(ivec2 block_min, ivec2 block_max) {
+    vec4 sum = vec4(0.0);
+    for (int y = block_min.y; y < block_max.y; ++y) {
+        for (int x = block_min.x; x < block_max.x; ++x) {
+            sum += fetch(ivec2(x, y));
+        }
+    }
+    ivec2 extent = block_max - block_min;
+    return sum / float(extent.x * extent.y);
+}
+
+// Picks the most frequent color in the block.  Colors are compared after quantizing to 5 bits
+// per channel so that shading noise does not split otherwise identical regions.
+vec4 filter_majority(ivec2 block_min, ivec2 block_max) {
+    ivec2 extent = block_max - block_min;
+    ivec2 stride = max(ivec2(1), extent / MAX_MAJORITY_SAMPLES);
+
+    vec4 best = fetch(block_min);
+    int best_count = 0;
+    for (int y0 = block_min.y; y0 < block_max.y; y0 += stride.y) {
+        for (int x0 = block_min.x; x0 < block_max.x; x0 += stride.x) {
+            vec4 c0 = fetch(ivec2(x0, y0));
+            uvec4 key0 = uvec4(c0 * 31.0 + 0.5);
+
+            int count = 0;
+            for (int y1 = block_min.y; y1 < block_max.y; y1 += stride.y) {
+                for (int x1 = block_min.x; x1 < block_max.x; x1 += stride.x) {
+                    uvec4 key1 = uvec4(fetch(ivec2(x1, y1)) * 31.0 + 0.5);
+                    if (key0 == key1) {
+                        count += 1;
+                    }
+                }
+            }
+
+            if (count > best_count) {
+                best_count = count;
+                best = c0;
+            }
+        }
+    }
+    return best;
+}
+
+// Kuwahara style filter: the block is split into four overlapping quadrants around its center
+// and the mean of the quadrant with the lowest luminance variance is kept.  Blocks straddling an
+// edge take the color of one side instead of a blend of both.
+vec4 filter_edge_preserving(ivec2 block_min, ivec2 block_max) {
+    ivec2 center = (block_min + block_max) / 2;
+    vec4 best = vec4(0.0);
+    float best_variance = 1.0e20;
+    for (int q = 0; q < 4; ++q) {
+        bool right = (q & 1) != 0;
+        bool bottom = (q & 2) != 0;
+        ivec2 qmin = ivec2(right ? center.x : block_min.x, bottom ? center.y : block_min.y);
+        ivec2 qmax = ivec2(right ? block_max.x : center.x + 1, bottom ? block_max.y : center.y + 1);
+        qmax = min(qmax, block_max);
+
+        vec4 sum = vec4(0.0);
+        float lum_sum = 0.0;
+        float lum_sq_sum = 0.0;
+        for (int y = qmin.y; y < qmax.y; ++y) {
+            for (int x = qmin.x; x < qmax.x; ++x) {
+                vec4 c = fetch(ivec2(x, y));
+                float l = luminance(c.rgb);
+                sum += c;
+                lum_sum += l;
+                lum_sq_sum += l * l;
+            }
+        }
+
+        ivec2 extent = max(qmax - qmin, ivec2(1));
+        float n = float(extent.x * extent.y);
+        float mean_lum = lum_sum / n;
+        float variance = lum_sq_sum / n - mean_lum * mean_lum;
+        if (variance < best_variance) {
+            best_variance = variance;
+            best = sum / n;
+        }
+    }
+    return best;
+}
+
+// Texel of the block closest to the filtered color `c`.  Opaque output pixels only consider
+// texels with some coverage, so they never map to the background.
+ivec2 closest_texel(ivec2 block_min, ivec2 block_max, vec4 c, bool opaque) {
+    ivec2 extent = block_max - block_min;
+    ivec2 stride = max(ivec2(1), extent / MAX_MAJORITY_SAMPLES);
+
+    ivec2 best = (block_min + block_max) / 2;
+    float best_dist = 1.0e20;
+    for (int y = block_min.y; y < block_max.y; y += stride.y) {
+        for (int x = block_min.x; x < block_max.x; x += stride.x) {
+            vec4 t = fetch(ivec2(x, y));
+            if (opaque && t.a == 0.0) {
+                continue;
+            }
+            vec4 d = t - c;
+            float dist = dot(d, d);
+            if (dist < best_dist) {
+                best_dist = dist;
+                best = ivec2(x, y);
+            }
+        }
+    }
+    return best;
+}
+
+void main()
+{
+    // Source texel block covered by this output pixel
+    vec2 scale = vec2(params.src_size) / vec2(params.dst_size);
+    vec2 dst_pixel = floor(gl_FragCoord.xy);
+    ivec2 block_min = ivec2(floor(dst_pixel * scale));
+    ivec2 block_max = max(ivec2(floor((dst_pixel + 1.0) * scale)), block_min + 1);
+    block_max = min(block_max, ivec2(params.src_size));
+
+    if (params.filter_mode == FILTER_BOX) {
+        color = filter_box(block_min, block_max);
+    } else if (params.filter_mode == FILTER_MAJORITY) {
+        color = filter_majority(block_min, block_max);
+    } else if (params.filter_mode == FILTER_EDGE_PRESERVING) {
+        color = filter_edge_preserving(block_min, block_max);
+    } else {
+        color = filter_nearest(block_min, block_max);
+    }
+
+    bool opaque = color.a > 0.0 && color.a >= params.alpha_threshold;
+    if (params.filter_mode == FILTER_NEAREST) {
+        source_texel = uvec2((block_min + block_max) / 2);
+    } else {
+        source_texel = uvec2(closest_texel(block_min, block_max, color, opaque));
+    }
+
+    // Hard-edged alpha for pixel art
+    if (opaque) {
+        color = vec4(color.rgb / color.a, 1.0);
+    } else {
+        color = vec4(0.0);
+    }
+}
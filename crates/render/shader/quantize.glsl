@@ -0,0 +1,200 @@
+#version 450
+
+// Maps each pixel of the pixelated image to the perceptually closest palette entry.  Distances
+// are measured in OKLab, see crates/palette/src/oklab.rs.  With an empty palette the input is
+// passed through unchanged.
+//
+// Compiled twice:
+//   * as a fragment shader writing the quantized image, applying ordered dithering or resolving
+//     the palette indices produced by error diffusion
+//   * with ERROR_DIFFUSION defined, as a compute shader dispatched once per step of a row
+//     wavefront.  Each row starts two pixels behind the one above, so a pixel can gather the
+//     quantization error of all its kernel neighbours from earlier steps and only ever writes
+//     its own error and palette index.
+
+#define MAX_PALETTE_COLORS 256
+
+#define DITHER_NONE 0u
+#define DITHER_ORDERED 1u
+#define DITHER_FLOYD_STEINBERG 2u
+#define DITHER_ATKINSON 3u
+
+struct PaletteEntry {
+    // OKLab color, w unused
+    vec4 lab;
+    // Linear RGB color, w unused
+    vec4 rgb;
+};
+
+#ifdef ERROR_DIFFUSION
+// One invocation per row
+layout (local_size_x = 64) in;
+#else
+// inputs
+layout (location = 0) in vec2 uv;
+
+// outputs
+layout (location = 0) out vec4 color;
+#endif // ERROR_DIFFUSION
+
+// uniforms
+layout (set = 0, binding = 0)
+uniform texture2D src_texture;
+
+layout (set = 0, binding = 1)
+uniform sampler src_sampler;
+
+layout (set = 0, binding = 2)
+uniform Palette {
+    uint count;
+    uint dither;
+    // Dither amplitude multiplier in [0,1]
+    float strength;
+    // Mean distance between neighbouring palette colors in linear RGB
+    float spread;
+    // Output pixel the ordered dither pattern is anchored to
+    ivec2 origin;
+    uvec2 size;
+    uint threshold_size;
+    PaletteEntry entries[MAX_PALETTE_COLORS];
+} palette;
+
+#ifdef ERROR_DIFFUSION
+layout (set = 1, binding = 0, r32ui)
+uniform writeonly uimage2D index_image;
+
+// Diffused quantization error of each pixel, already scaled by the dither strength
+layout (set = 1, binding = 1)
+buffer Errors {
+    vec4 errors[];
+};
+
+layout (set = 2, binding = 0)
+uniform Step {
+    uint step;
+};
+#else
+layout (set = 1, binding = 0)
+uniform texture2D threshold_texture;
+
+// Palette indices chosen by error diffusion
+layout (set = 1, binding = 1)
+uniform utexture2D index_texture;
+#endif // ERROR_DIFFUSION
+
+float cbrt(float x) {
+    return sign(x) * pow(abs(x), 1.0 / 3.0);
+}
+
+vec3 linear_to_oklab(vec3 c) {
+    float l = 0.4122214708 * c.r + 0.5363325363 * c.g + 0.0514459929 * c.b;
+    float m = 0.2119034982 * c.r + 0.6806995451 * c.g + 0.1073969566 * c.b;
+    float s = 0.0883024619 * c.r + 0.2817188376 * c.g + 0.6299787005 * c.b;
+
+    l = cbrt(l);
+    m = cbrt(m);
+    s = cbrt(s);
+
+    return vec3(
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s
+    );
+}
+
+uint nearest_entry(vec3 lab) {
+    uint best = 0u;
+    float best_dist = 1.0e20;
+    for (uint i = 0u; i < palette.count; ++i) {
+        vec3 d = palette.entries[i].lab.xyz - lab;
+        float dist = dot(d, d);
+        if (dist < best_dist) {
+            best_dist = dist;
+            best = i;
+        }
+    }
+    return best;
+}
+
+vec4 fetch(ivec2 p) {
+    return texelFetch(sampler2D(src_texture, src_sampler), p, 0);
+}
+
+#ifdef ERROR_DIFFUSION
+
+// Error of pixel `p`, zero outside the image
+vec3 error_at(ivec2 p) {
+    if (p.x < 0 || p.y < 0 || p.x >= int(palette.size.x) || p.y >= int(palette.size.y)) {
+        return vec3(0.0);
+    }
+    return errors[uint(p.y) * palette.size.x + uint(p.x)].rgb;
+}
+
+void main()
+{
+    // Pixel (x, y) is handled in step x + 2y, after (x + 1, y - 1) and every other neighbour
+    // the kernels gather from
+    int y = int(gl_GlobalInvocationID.x);
+    int x = int(step) - 2 * y;
+    if (y >= int(palette.size.y) || x < 0 || x >= int(palette.size.x)) {
+        return;
+    }
+    ivec2 p = ivec2(x, y);
+    uint idx = uint(y) * palette.size.x + uint(x);
+
+    // Transparent pixels are left alone and pass no error on
+    vec4 src = fetch(p);
+    if (src.a == 0.0) {
+        errors[idx] = vec4(0.0);
+        return;
+    }
+
+    vec3 incoming;
+    if (palette.dither == DITHER_ATKINSON) {
+        // 1/8 from each of six neighbours, 1/4 of the error is dropped
+        incoming = (error_at(p + ivec2(-1, 0)) + error_at(p + ivec2(-2, 0))
+            + error_at(p + ivec2(1, -1)) + error_at(p + ivec2(0, -1))
+            + error_at(p + ivec2(-1, -1)) + error_at(p + ivec2(0, -2))) / 8.0;
+    } else {
+        incoming = error_at(p + ivec2(-1, 0)) * (7.0 / 16.0)
+            + error_at(p + ivec2(1, -1)) * (3.0 / 16.0)
+            + error_at(p + ivec2(0, -1)) * (5.0 / 16.0)
+            + error_at(p + ivec2(-1, -1)) * (1.0 / 16.0);
+    }
+
+    vec3 c = src.rgb + incoming;
+    uint entry = nearest_entry(linear_to_oklab(max(c, vec3(0.0))));
+    imageStore(index_image, p, uvec4(entry));
+    errors[idx] = vec4((c - palette.entries[entry].rgb.rgb) * palette.strength, 0.0);
+}
+
+#else
+
+void main()
+{
+    ivec2 p = ivec2(gl_FragCoord.xy);
+    vec4 src = fetch(p);
+    if (palette.count == 0u || src.a == 0.0) {
+        color = src;
+        return;
+    }
+
+    uint idx;
+    if (palette.dither == DITHER_FLOYD_STEINBERG || palette.dither == DITHER_ATKINSON) {
+        idx = texelFetch(usampler2D(index_texture, src_sampler), p, 0).r;
+    } else if (palette.dither == DITHER_ORDERED) {
+        // Threshold in [0,1) offsets the color by up to half the palette spacing either way.
+        // The pattern is anchored to `origin` so it travels with the sprite rather than the
+        // screen.
+        ivec2 n = ivec2(int(palette.threshold_size));
+        ivec2 t = ((p - palette.origin) % n + n) % n;
+        float threshold = texelFetch(sampler2D(threshold_texture, src_sampler), t, 0).r;
+        vec3 c = src.rgb + (threshold - 0.5) * palette.spread * palette.strength;
+        idx = nearest_entry(linear_to_oklab(max(c, vec3(0.0))));
+    } else {
+        idx = nearest_entry(linear_to_oklab(src.rgb));
+    }
+    color = vec4(palette.entries[idx].rgb.rgb, src.a);
+}
+
+#endif // ERROR_DIFFUSION
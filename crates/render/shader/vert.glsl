@@ -8,7 +8,18 @@ layout (location = 1) in vec3 normal;
 #endif // HAS_NORMALS
 #ifdef HAS_TEXCOORDS
 layout (location = 2) in vec2 texcoord;
-#endif // HAS_NORMALS
+#endif // HAS_TEXCOORDS
+#ifdef HAS_JOINTS
+layout (location = 3) in uvec4 joints;
+layout (location = 4) in vec4 weights;
+#endif // HAS_JOINTS
+#ifdef HAS_TANGENTS
+// XYZ tangent, W bitangent sign
+layout (location = 5) in vec4 tangent;
+#endif // HAS_TANGENTS
+#ifdef HAS_COLORS
+layout (location = 6) in vec4 color;
+#endif // HAS_COLORS
 
 // outputs
 layout (location = 0) out vec3 world_pos;
@@ -17,28 +28,102 @@ layout (location = 1) out vec3 object_pos;
 layout (location = 2) out vec3 object_normal;
 #endif // HAS_NORMALS
 layout (location = 3) out vec2 uv;
+// Distance in front of the camera, linear in both perspective and orthographic projections
+layout (location = 4) out float view_depth;
+#ifdef HAS_TANGENTS
+layout (location = 5) out vec4 object_tangent;
+#endif // HAS_TANGENTS
+#ifdef HAS_COLORS
+layout (location = 6) out vec4 vertex_color;
+#endif // HAS_COLORS
 
 // uniforms
 layout (set = 0, binding = 0)
 uniform mat4 view_proj_transform;
 
+layout (set = 0, binding = 1)
+uniform mat4 view_transform;
+
 layout (set = 1, binding = 0)
 uniform mat4 model_transform;
 
+#ifdef HAS_JOINTS
+// Skinning matrix per joint, taking bind pose positions to model space
+layout (set = 1, binding = 3)
+readonly buffer Joints {
+    mat4 joint_matrices[];
+};
+#endif // HAS_JOINTS
+
+#ifdef HAS_MORPH_TARGETS
+// Position, normal and tangent deltas, indexed [target][attribute][vertex]
+layout (set = 1, binding = 4)
+readonly buffer MorphDeltas {
+    vec4 morph_deltas[];
+};
+
+layout (set = 1, binding = 5)
+readonly buffer MorphWeights {
+    uint morph_target_count;
+    uint morph_vertex_count;
+    float morph_weights[];
+};
+#endif // HAS_MORPH_TARGETS
+
 void main() {
-    vec4 world_p = model_transform * vec4(pos, 1.0); 
+#ifdef HAS_JOINTS
+    // Linear blend skinning
+    mat4 skin_transform =
+        weights.x * joint_matrices[joints.x] +
+        weights.y * joint_matrices[joints.y] +
+        weights.z * joint_matrices[joints.z] +
+        weights.w * joint_matrices[joints.w];
+#else
+    mat4 skin_transform = mat4(1.0);
+#endif // HAS_JOINTS
+    // Morph targets are applied in the mesh's bind pose, before skinning
+    vec3 morphed_pos = pos;
+#ifdef HAS_NORMALS
+    vec3 morphed_normal = normal;
+#endif // HAS_NORMALS
+#ifdef HAS_TANGENTS
+    vec3 morphed_tangent = tangent.xyz;
+#endif // HAS_TANGENTS
+#ifdef HAS_MORPH_TARGETS
+    for (uint t = 0u; t < morph_target_count; ++t) {
+        uint delta_idx = 3u * t * morph_vertex_count + uint(gl_VertexIndex);
+        morphed_pos += morph_weights[t] * morph_deltas[delta_idx].xyz;
+#ifdef HAS_NORMALS
+        morphed_normal += morph_weights[t] * morph_deltas[delta_idx + morph_vertex_count].xyz;
+#endif // HAS_NORMALS
+#ifdef HAS_TANGENTS
+        morphed_tangent += morph_weights[t] * morph_deltas[delta_idx + 2u * morph_vertex_count].xyz;
+#endif // HAS_TANGENTS
+    }
+#endif // HAS_MORPH_TARGETS
+
+    vec4 skinned_p = skin_transform * vec4(morphed_pos, 1.0);
+
+    vec4 world_p = model_transform * skinned_p;
     gl_Position = view_proj_transform * world_p; 
+    view_depth = -(view_transform * world_p).z;
 
     world_pos = world_p.xyz;
-    object_pos = pos;
+    object_pos = skinned_p.xyz;
 #ifdef HAS_NORMALS
-    object_normal = normal;
+    object_normal = mat3(skin_transform) * morphed_normal;
 #endif // HAS_NORMALS
+#ifdef HAS_TANGENTS
+    object_tangent = vec4(mat3(skin_transform) * morphed_tangent, tangent.w);
+#endif // HAS_TANGENTS
+#ifdef HAS_COLORS
+    vertex_color = color;
+#endif // HAS_COLORS
 
 #ifdef HAS_TEXCOORDS
     uv = texcoord;
 #else
-    uv = vec2(0.0, 0,0);
+    uv = vec2(0.0);
 #endif // HAS_TEXCOORDS
     
 }
@@ -53,9 +53,9 @@ impl CameraView {
         self.dolly_translation += glam::Vec3::new(0.0, 0.0, amount);
     }
 
-    fn pan(&mut self, amount: glam::Vec2) {
-        let dolly_scale = self.dolly_translation.z;
-        let pan = glam::Vec3::new(amount.x * dolly_scale, amount.y * dolly_scale, 0.0);
+    /// Pan by `amount` NDC units, with NDC distances scaled by `scale` to view space
+    fn pan(&mut self, amount: glam::Vec2, scale: f32) {
+        let pan = glam::Vec3::new(amount.x * scale, amount.y * scale, 0.0);
 
         // transform to world space
         let inv_view = self.matrix().inverse();
@@ -85,28 +85,158 @@ fn pos_to_arcball(pos: glam::Vec2) -> glam::Quat {
     }
 }
 
+/// How the view is projected onto the image plane
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Projection {
+    /// Vertical field of view in radians
+    Perspective { fovy: f32 },
+    /// Parallel projection.  Each pixel of the pixel-art output covers `units_per_pixel` world
+    /// units on both axes.
+    Orthographic { units_per_pixel: f32 },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective {
+            fovy: std::f32::consts::PI / 4.0,
+        }
+    }
+}
+
+impl Projection {
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self, Projection::Orthographic { .. })
+    }
+}
+
+/// Standard sprite viewing directions.  All presets orbit the model's center and use an
+/// orthographic projection.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CameraPreset {
+    /// Equal foreshortening of all three axes, 35.26 degrees down at a 45 degree corner
+    Isometric,
+    /// Pixel-art "isometric": 30 degrees down at a 45 degree corner, ground lines step two
+    /// pixels across per pixel up
+    Dimetric,
+    /// Straight down, model front towards the bottom of the image
+    TopDown,
+    /// From the model's right side (+X), level
+    Side,
+    /// From the front, 45 degrees down
+    ThreeQuarter,
+}
+
+impl CameraPreset {
+    pub const ALL: [CameraPreset; 5] = [
+        CameraPreset::Isometric,
+        CameraPreset::Dimetric,
+        CameraPreset::TopDown,
+        CameraPreset::Side,
+        CameraPreset::ThreeQuarter,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraPreset::Isometric => "isometric",
+            CameraPreset::Dimetric => "dimetric",
+            CameraPreset::TopDown => "top-down",
+            CameraPreset::Side => "side",
+            CameraPreset::ThreeQuarter => "three-quarter",
+        }
+    }
+
+    /// Camera yaw about +Y from the +Z axis and pitch down from the horizon, in degrees
+    pub fn yaw_pitch(&self) -> (f32, f32) {
+        match self {
+            CameraPreset::Isometric => (45.0, 1.0f32.atan2(std::f32::consts::SQRT_2).to_degrees()),
+            CameraPreset::Dimetric => (45.0, 30.0),
+            CameraPreset::TopDown => (0.0, 90.0),
+            CameraPreset::Side => (90.0, 0.0),
+            CameraPreset::ThreeQuarter => (0.0, 45.0),
+        }
+    }
+
+    /// Unit direction from the look-at point towards the eye, and the view up vector
+    pub fn eye_direction(&self) -> (glam::Vec3, glam::Vec3) {
+        let (yaw, pitch) = self.yaw_pitch();
+        let (yaw, pitch) = (yaw.to_radians(), pitch.to_radians());
+        let horizontal = glam::Vec3::new(yaw.sin(), 0.0, yaw.cos());
+        let dir = horizontal * pitch.cos() + glam::Vec3::Y * pitch.sin();
+        // Looking straight down the horizontal direction the camera came from is up
+        let up = if pitch.cos() < 1e-4 {
+            -horizontal
+        } else {
+            glam::Vec3::Y
+        };
+        (dir.normalize(), up)
+    }
+}
+
+impl std::str::FromStr for CameraPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CameraPreset::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = CameraPreset::ALL.iter().map(|p| p.name()).collect();
+                format!("unknown camera '{}', expected {}", s, names.join(", "))
+            })
+    }
+}
+
 /// Camera projection controller.  Allows initial setup of projection transform.  No interactivity.
 struct CameraProjection {
     aspect: f32,
-    fovy: f32,
+    projection: Projection,
     znear: f32,
     zfar: f32,
+    /// Rows of the pixel-art output, which orthographic scales are relative to
+    pixel_height: f32,
 }
 
 impl Default for CameraProjection {
     fn default() -> Self {
         CameraProjection {
             aspect: 1.0,
-            fovy: std::f32::consts::PI / 4.0,
+            projection: Projection::default(),
             znear: 0.0001,
             zfar: 100.0,
+            pixel_height: 64.0,
         }
     }
 }
 
 impl CameraProjection {
+    /// Half the view height at unit distance (perspective) or in world units (orthographic)
+    fn half_height(&self) -> f32 {
+        match self.projection {
+            Projection::Perspective { fovy } => (fovy * 0.5).tan(),
+            Projection::Orthographic { units_per_pixel } => {
+                units_per_pixel * self.pixel_height * 0.5
+            }
+        }
+    }
+
     fn matrix(&self) -> glam::Mat4 {
-        glam::Mat4::perspective_rh(self.fovy, self.aspect, self.znear, self.zfar)
+        match self.projection {
+            Projection::Perspective { fovy } => {
+                glam::Mat4::perspective_rh(fovy, self.aspect, self.znear, self.zfar)
+            }
+            Projection::Orthographic { .. } => {
+                let half_height = self.half_height();
+                let half_width = half_height * self.aspect;
+                glam::Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.znear,
+                    self.zfar,
+                )
+            }
+        }
     }
 }
 
@@ -114,6 +244,8 @@ impl CameraProjection {
 pub struct Camera {
     camera_view: CameraView,
     camera_projection: CameraProjection,
+    /// Quantize the view translation to whole pixel-art texels, see `view_matrix`
+    snap: bool,
 }
 
 impl Camera {
@@ -129,27 +261,114 @@ impl Camera {
     pub fn with_projection(mut self, aspect: f32, fovy: f32, znear: f32, zfar: f32) -> Camera {
         self.camera_projection = CameraProjection {
             aspect,
-            fovy,
+            projection: Projection::Perspective { fovy },
             znear,
             zfar,
+            ..self.camera_projection
         };
         self
     }
 
+    /// Replace the view, discarding interactive rotation, panning and dollying
+    pub fn set_view(&mut self, eye: glam::Vec3, look_at: glam::Vec3, up: glam::Vec3) {
+        self.camera_view = CameraView::new(eye, look_at, up);
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.camera_projection.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.camera_projection.projection = projection;
+    }
+
+    /// Rows of the pixel-art output, the reference for `Projection::Orthographic` scales
+    pub fn set_pixel_height(&mut self, pixel_height: f32) {
+        self.camera_projection.pixel_height = pixel_height;
+    }
+
     pub fn rotate(&mut self, pos0: glam::Vec2, pos1: glam::Vec2) {
         self.camera_view.rotate(pos0, pos1);
     }
 
+    /// Move towards the look-at point by `amount` world units.  Orthographic views don't change
+    /// with distance, so they zoom by the same relative amount instead.
     pub fn dolly(&mut self, amount: f32) {
-        self.camera_view.dolly(amount);
+        let distance = -self.camera_view.dolly_translation.z;
+        match &mut self.camera_projection.projection {
+            Projection::Orthographic { units_per_pixel } if distance > 0.0 => {
+                *units_per_pixel *= (-amount / distance).exp();
+            }
+            _ => self.camera_view.dolly(amount),
+        }
     }
 
     pub fn pan(&mut self, amount: glam::Vec2) {
-        self.camera_view.pan(amount);
+        let scale = match self.camera_projection.projection {
+            Projection::Perspective { .. } => self.camera_view.dolly_translation.z,
+            Projection::Orthographic { .. } => -self.camera_projection.half_height(),
+        };
+        self.camera_view.pan(amount, scale);
+    }
+
+    pub fn snap(&self) -> bool {
+        self.snap
+    }
+
+    /// Snap the view to the pixel-art texel grid, so the grid stays fixed relative to the world
+    /// as the camera pans.  Only orthographic views have a texel size independent of depth,
+    /// perspective views are never snapped.
+    pub fn set_snap(&mut self, snap: bool) {
+        self.snap = snap;
+    }
+
+    /// View space size of a pixel-art texel, if it doesn't depend on depth
+    pub fn texel_size(&self) -> Option<f32> {
+        match self.camera_projection.projection {
+            Projection::Orthographic { units_per_pixel } => Some(units_per_pixel),
+            Projection::Perspective { .. } => None,
+        }
+    }
+
+    /// View space translation dropped by snapping, under half a texel on each axis
+    fn snap_remainder(&self) -> glam::Vec2 {
+        match (self.snap, self.texel_size()) {
+            (true, Some(texel)) => {
+                let translation = self.camera_view.matrix().w_axis.truncate().truncate();
+                translation - (translation / texel).round() * texel
+            }
+            _ => glam::Vec2::ZERO,
+        }
+    }
+
+    /// Offset in pixel-art pixels (y down) that shifts the snapped image to where the unsnapped
+    /// camera would have drawn it.  Displaying the output shifted by this keeps camera motion
+    /// smooth while the sprite itself only ever moves by whole texels.
+    pub fn snap_offset(&self) -> glam::Vec2 {
+        match self.texel_size() {
+            Some(texel) => {
+                let remainder = self.snap_remainder() / texel;
+                glam::Vec2::new(remainder.x, -remainder.y)
+            }
+            None => glam::Vec2::ZERO,
+        }
+    }
+
+    /// World space translation that moves `point` onto the texel grid of the view, eg to snap
+    /// root motion.  Zero for perspective views.
+    pub fn snap_translation(&self, point: glam::Vec3) -> glam::Vec3 {
+        let Some(texel) = self.texel_size() else {
+            return glam::Vec3::ZERO;
+        };
+        let view = self.view_matrix();
+        let p = view.transform_point3(point).truncate();
+        let delta = (p / texel).round() * texel - p;
+        view.inverse().transform_vector3(delta.extend(0.0))
     }
 
     pub fn view_matrix(&self) -> glam::Mat4 {
-        self.camera_view.matrix()
+        let remainder = self.snap_remainder();
+        glam::Mat4::from_translation((-remainder).extend(0.0)) * self.camera_view.matrix()
     }
 
     pub fn projection_matrix(&self) -> glam::Mat4 {
@@ -158,7 +377,7 @@ impl Camera {
 
     #[allow(unused)]
     pub fn view_projection_matrix(&self) -> glam::Mat4 {
-        let view = self.camera_view.matrix();
+        let view = self.view_matrix();
         let proj = self.camera_projection.matrix();
         proj * view
     }
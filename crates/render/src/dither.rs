@@ -0,0 +1,201 @@
+/// Dithering applied while quantizing to a palette
+#[derive(Default, Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Dither {
+    #[default]
+    None,
+    /// Ordered dithering with 2x2, 4x4 or 8x8 Bayer matrices
+    Bayer2,
+    Bayer4,
+    Bayer8,
+    /// Ordered dithering with a 64x64 void-and-cluster blue noise threshold map
+    BlueNoise,
+    /// Error diffusion.  Not temporally stable: small changes in the input can reshuffle the
+    /// pattern of a whole region
+    FloydSteinberg,
+    Atkinson,
+}
+
+impl Dither {
+    pub const ALL: [Dither; 7] = [
+        Dither::None,
+        Dither::Bayer2,
+        Dither::Bayer4,
+        Dither::Bayer8,
+        Dither::BlueNoise,
+        Dither::FloydSteinberg,
+        Dither::Atkinson,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dither::None => "None",
+            Dither::Bayer2 => "Bayer 2x2",
+            Dither::Bayer4 => "Bayer 4x4",
+            Dither::Bayer8 => "Bayer 8x8",
+            Dither::BlueNoise => "Blue noise",
+            Dither::FloydSteinberg => "Floyd-Steinberg",
+            Dither::Atkinson => "Atkinson",
+        }
+    }
+
+    /// Mode constant understood by quantize.glsl
+    pub fn shader_mode(&self) -> u32 {
+        match self {
+            Dither::None => 0,
+            Dither::Bayer2 | Dither::Bayer4 | Dither::Bayer8 | Dither::BlueNoise => 1,
+            Dither::FloydSteinberg => 2,
+            Dither::Atkinson => 3,
+        }
+    }
+
+    pub fn is_error_diffusion(&self) -> bool {
+        matches!(self, Dither::FloydSteinberg | Dither::Atkinson)
+    }
+
+    /// Width and height of the threshold map of ordered modes
+    pub fn threshold_size(&self) -> Option<u32> {
+        match self {
+            Dither::Bayer2 => Some(2),
+            Dither::Bayer4 => Some(4),
+            Dither::Bayer8 => Some(8),
+            Dither::BlueNoise => Some(BLUE_NOISE_SIZE),
+            _ => None,
+        }
+    }
+
+    /// Row-major threshold map values in [0,1) for ordered modes, `threshold_size` squared of
+    /// them.  Blue noise takes a while to generate, callers should keep the result.
+    pub fn threshold_map(&self) -> Option<Vec<f32>> {
+        match self {
+            Dither::BlueNoise => Some(blue_noise(BLUE_NOISE_SIZE)),
+            _ => self.threshold_size().map(bayer),
+        }
+    }
+}
+
+const BLUE_NOISE_SIZE: u32 = 64;
+
+/// Bayer index matrix of power-of-two size `n`, normalized to [0,1)
+fn bayer(n: u32) -> Vec<f32> {
+    let mut m = vec![0u32];
+    let mut size = 1;
+    // M_2k = [ 4M     4M+2 ]
+    //        [ 4M+3   4M+1 ]
+    while size < n {
+        let next_size = size * 2;
+        let mut next = vec![0u32; (next_size * next_size) as usize];
+        for y in 0..size {
+            for x in 0..size {
+                let v = 4 * m[(y * size + x) as usize];
+                next[(y * next_size + x) as usize] = v;
+                next[(y * next_size + x + size) as usize] = v + 2;
+                next[((y + size) * next_size + x) as usize] = v + 3;
+                next[((y + size) * next_size + x + size) as usize] = v + 1;
+            }
+        }
+        m = next;
+        size = next_size;
+    }
+
+    let count = (n * n) as f32;
+    m.into_iter().map(|v| (v as f32 + 0.5) / count).collect()
+}
+
+/// Blue noise threshold map of size `n` using Ulichney's void-and-cluster method with a toroidal
+/// gaussian energy filter.  Deterministic.
+fn blue_noise(n: u32) -> Vec<f32> {
+    const SIGMA: f32 = 1.5;
+    let n = n as usize;
+    let count = n * n;
+
+    // Gaussian weight by toroidal offset
+    let mut kernel = vec![0.0f32; count];
+    for dy in 0..n {
+        for dx in 0..n {
+            let wx = dx.min(n - dx) as f32;
+            let wy = dy.min(n - dy) as f32;
+            kernel[dy * n + dx] = (-(wx * wx + wy * wy) / (2.0 * SIGMA * SIGMA)).exp();
+        }
+    }
+
+    let update = |energy: &mut [f32], idx: usize, sign: f32| {
+        let (px, py) = (idx % n, idx / n);
+        for y in 0..n {
+            for x in 0..n {
+                let dx = (x + n - px) % n;
+                let dy = (y + n - py) % n;
+                energy[y * n + x] += sign * kernel[dy * n + dx];
+            }
+        }
+    };
+    let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+        (0..count)
+            .filter(|&i| pattern[i])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+    let largest_void = |pattern: &[bool], energy: &[f32]| {
+        (0..count)
+            .filter(|&i| !pattern[i])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+
+    // Initial pattern: ~10% of pixels from a fixed xorshift sequence
+    let mut pattern = vec![false; count];
+    let mut energy = vec![0.0f32; count];
+    let mut state = 0x2545f491u32;
+    let mut ones = 0;
+    while ones < count / 10 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let idx = state as usize % count;
+        if !pattern[idx] {
+            pattern[idx] = true;
+            update(&mut energy, idx, 1.0);
+            ones += 1;
+        }
+    }
+
+    // Relax initial pattern: move the tightest cluster into the largest void until stable
+    for _ in 0..count {
+        let cluster = tightest_cluster(&pattern, &energy);
+        pattern[cluster] = false;
+        update(&mut energy, cluster, -1.0);
+
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0usize; count];
+
+    // Phase 1: remove points from a copy of the initial pattern, tightest clusters first
+    {
+        let mut pattern = pattern.clone();
+        let mut energy = energy.clone();
+        for r in (0..ones).rev() {
+            let cluster = tightest_cluster(&pattern, &energy);
+            pattern[cluster] = false;
+            update(&mut energy, cluster, -1.0);
+            rank[cluster] = r;
+        }
+    }
+
+    // Phases 2 and 3: fill the largest voids until every pixel is ranked
+    for r in ones..count {
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        update(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+
+    rank.into_iter()
+        .map(|r| (r as f32 + 0.5) / count as f32)
+        .collect()
+}
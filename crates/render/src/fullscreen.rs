@@ -0,0 +1,198 @@
+// Helpers shared by the image-space passes (pixelize, quantize, ...).  Each pass draws a single
+// fullscreen triangle and reads its input with texelFetch in the fragment shader.
+
+/// Layout for a pass reading one source texture through a non-filtering sampler, configured by
+/// a single uniform block.  Also visible to compute stages so helper compute passes can share
+/// the bind group:
+///   binding 0: source texture
+///   binding 1: sampler
+///   binding 2: uniform buffer
+pub fn bind_group_layout(label: &str, device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+        label: Some(label),
+    })
+}
+
+/// Bind group matching `bind_group_layout`
+pub fn bind_group(
+    label: &str,
+    layout: &wgpu::BindGroupLayout,
+    source: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+    device: &wgpu::Device,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some(label),
+    })
+}
+
+pub fn nearest_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    })
+}
+
+/// Pipeline drawing a fullscreen triangle with the given GLSL fragment shader
+pub fn create_pipeline(
+    label: &str,
+    frag_shader: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    device: &wgpu::Device,
+) -> wgpu::RenderPipeline {
+    create_multi_target_pipeline(label, frag_shader, bind_group_layouts, &[format], device)
+}
+
+/// `create_pipeline` for fragment shaders writing one output per entry of `formats`
+pub fn create_multi_target_pipeline(
+    label: &str,
+    frag_shader: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    formats: &[wgpu::TextureFormat],
+    device: &wgpu::Device,
+) -> wgpu::RenderPipeline {
+    let vert_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("FullscreenShader"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: include_str!("../shader/fullscreen.glsl").into(),
+            stage: wgpu::naga::ShaderStage::Vertex,
+            defines: Default::default(),
+        },
+    });
+    let frag_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Glsl {
+            shader: frag_shader.into(),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines: Default::default(),
+        },
+    });
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &vert_module,
+            entry_point: Some("main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &frag_module,
+            entry_point: Some("main"),
+            targets: &formats
+                .iter()
+                .map(|&format| Some(format.into()))
+                .collect::<Vec<_>>(),
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Record and submit a single fullscreen draw into `target`
+pub fn draw(
+    label: &str,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    target: &wgpu::TextureView,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) {
+    draw_multi_target(label, pipeline, bind_group, &[target], device, queue);
+}
+
+/// `draw` for pipelines from `create_multi_target_pipeline`, `targets` in output order
+pub fn draw_multi_target(
+    label: &str,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+    targets: &[&wgpu::TextureView],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) {
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some(label) });
+    {
+        let color_attachments: Vec<_> = targets
+            .iter()
+            .map(|&view| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })
+            })
+            .collect();
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+    queue.submit(std::iter::once(encoder.finish()));
+}
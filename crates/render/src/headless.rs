@@ -0,0 +1,78 @@
+//! Device creation without a window or surface, for batch rendering and CI.
+
+/// Errors produced while creating a headless device
+#[derive(Debug)]
+pub enum Error {
+    /// No adapter matched the request.  With `software` set this means no CPU rasterizer
+    /// (eg, lavapipe, llvmpipe or WARP) is installed.
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NoAdapter => write!(f, "no suitable graphics adapter found"),
+            Error::RequestDevice(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        Error::RequestDevice(e)
+    }
+}
+
+/// Create a device and queue on any available backend.  With `software` set only CPU adapters
+/// are considered, which keeps output independent of the host GPU.
+pub fn request_device(software: bool) -> Result<(wgpu::Device, wgpu::Queue), Error> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    let adapter = if software {
+        // Backends without a dedicated fallback adapter (eg, GL) may still expose a CPU device
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: true,
+            compatible_surface: None,
+        }))
+        .or_else(|| find_cpu_adapter(&instance))
+    } else {
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+    };
+    let adapter = adapter.ok_or(Error::NoAdapter)?;
+
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("Headless Device"),
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::default(),
+            memory_hints: wgpu::MemoryHints::default(),
+        },
+        None,
+    ))?;
+    Ok((device, queue))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn find_cpu_adapter(instance: &wgpu::Instance) -> Option<wgpu::Adapter> {
+    instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .into_iter()
+        .find(|a| a.get_info().device_type == wgpu::DeviceType::Cpu)
+}
+
+/// Adapters can't be enumerated on the web
+#[cfg(target_arch = "wasm32")]
+fn find_cpu_adapter(_instance: &wgpu::Instance) -> Option<wgpu::Adapter> {
+    None
+}
@@ -1,13 +1,62 @@
 mod camera;
+mod dither;
+mod fullscreen;
+pub mod headless;
 mod light;
+mod maps;
+mod outline;
+mod pipeline;
+mod pixelize;
+mod quantize;
 mod scene;
 mod shader;
+#[cfg(test)]
+mod tests;
 mod texture;
+mod turntable;
 
 use scene::*;
 
+pub use camera::{CameraPreset, Projection};
+pub use dither::Dither;
+pub use light::CelShading;
+pub use maps::SpriteMap;
+pub use outline::{Edges as OutlineEdges, LineColor, Outline, Technique as OutlineTechnique};
+pub use pixelize::Filter as PixelFilter;
+pub use shader::ShadingModel;
+pub use turntable::{Turntable, TurntableFrame};
+
 //use wgpu::{util::DeviceExt, PrimitiveTopology};
 
+/// Face culling of the main render pass
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CullMode {
+    /// Cull back faces unless the material is double-sided
+    #[default]
+    Material,
+    Back,
+    Front,
+    None,
+}
+
+impl CullMode {
+    pub const ALL: [CullMode; 4] = [
+        CullMode::Material,
+        CullMode::Back,
+        CullMode::Front,
+        CullMode::None,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CullMode::Material => "Material",
+            CullMode::Back => "Back",
+            CullMode::Front => "Front",
+            CullMode::None => "None",
+        }
+    }
+}
+
 /// Simple renderer for single 3D model
 ///
 /// Handles user events (eg, mouse drag) and renders model to offscreen texture.
@@ -17,13 +66,29 @@ pub struct Renderer {
     device: wgpu::Device,
     render_view: RenderView,
     scene: Scene,
-    _shader_cache: shader::Cache,
+    pipeline_cache: pipeline::Cache,
+    shading: ShadingModel,
+    cull_mode: CullMode,
+    model_yaw: f32,
+    snap_root_motion: bool,
+    background: [f32; 4],
 
     render_pipelines: Vec<wgpu::RenderPipeline>,
     depth_texture: texture::Texture,
+    edge_buffers: outline::EdgeBuffers,
+
+    outline: Outline,
+    hull: outline::Hull,
+
+    pixelizer: pixelize::Pixelizer,
+    outliner: outline::Outliner,
+    quantizer: quantize::Quantizer,
+    maps: maps::MapRenderer,
 }
 
 impl Renderer {
+    pub const DEFAULT_PIXEL_SIZE: [u32; 2] = [64, 64];
+
     pub fn new(
         size: &[u32; 2],
         device: wgpu::Device,
@@ -32,21 +97,59 @@ impl Renderer {
     ) -> Self {
         let render_view = RenderView::new(size, &device);
 
-        let scene = Scene::from_model(input_model, &device);
-
-        let mut shader_cache = shader::Cache::new(&device);
-        let render_pipelines = generate_pipelines(&scene, &mut shader_cache, &render_view, &device);
+        let mut scene = Scene::from_model(input_model, &device, &queue);
+
+        let shading = ShadingModel::default();
+        let mut pipeline_cache = pipeline::Cache::new(&scene.layouts, &device);
+        let cull_mode = CullMode::default();
+        let render_pipelines = generate_pipelines(
+            &scene,
+            shading,
+            cull_mode,
+            &mut pipeline_cache,
+            &render_view,
+        );
 
         let depth_texture = texture::Texture::new_depth_texture(size, &device);
+        let edge_buffers = outline::EdgeBuffers::new(size, &device);
+
+        let outline = Outline::default();
+        let hull = outline::Hull::new(outline.edges, &scene.layouts, &device);
+
+        let pixelizer = pixelize::Pixelizer::new(&Self::DEFAULT_PIXEL_SIZE, &render_view, &device);
+        let outliner = outline::Outliner::new(
+            &pixelizer.target,
+            &edge_buffers,
+            render_view.size(),
+            &device,
+        );
+        let quantizer = quantize::Quantizer::new(&outliner.target, &device, &queue);
+        let maps = maps::MapRenderer::new(&pixelizer, &quantizer.target, &edge_buffers, &device);
+        scene
+            .camera
+            .controller
+            .set_pixel_height(pixelizer.target.sizef().y);
 
         Self {
             queue,
             device,
             render_view,
-            _shader_cache: shader_cache,
+            pipeline_cache,
+            shading,
+            cull_mode,
+            model_yaw: 0.0,
+            snap_root_motion: false,
+            background: [0.0; 4],
             scene,
             render_pipelines,
             depth_texture,
+            edge_buffers,
+            outline,
+            hull,
+            pixelizer,
+            outliner,
+            quantizer,
+            maps,
         }
     }
 
@@ -54,6 +157,367 @@ impl Renderer {
         &self.render_view.view
     }
 
+    /// Low resolution output of the pixelization and quantization passes.  The view is replaced
+    /// whenever the pixel size changes, so callers holding on to it must refresh it after
+    /// `set_pixel_size`.
+    pub fn get_pixel_texture_view(&self) -> &wgpu::TextureView {
+        &self.quantizer.target.view
+    }
+
+    pub fn pixel_size(&self) -> [u32; 2] {
+        self.pixelizer.target.size().into()
+    }
+
+    /// Set the pixel-art output resolution.  Clamped to [16, 256] per dimension.
+    pub fn set_pixel_size(&mut self, size: &[u32; 2]) {
+        self.pixelizer.resize(size, &self.device);
+        self.outliner.resize(&self.pixelizer.target, &self.device);
+        self.quantizer.resize(&self.outliner.target, &self.device);
+        self.maps.resize(
+            &self.pixelizer,
+            &self.quantizer.target,
+            &self.edge_buffers,
+            &self.device,
+        );
+        self.scene
+            .camera
+            .controller
+            .set_pixel_height(self.pixelizer.target.sizef().y);
+    }
+
+    pub fn pixel_filter(&self) -> PixelFilter {
+        self.pixelizer.filter()
+    }
+
+    pub fn set_pixel_filter(&mut self, filter: PixelFilter) {
+        self.pixelizer.set_filter(filter);
+    }
+
+    pub fn alpha_threshold(&self) -> f32 {
+        self.pixelizer.alpha_threshold()
+    }
+
+    /// Coverage above which a pixel-art pixel is opaque, see `Pixelizer::set_alpha_threshold`
+    pub fn set_alpha_threshold(&mut self, threshold: f32) {
+        self.pixelizer.set_alpha_threshold(threshold);
+    }
+
+    pub fn model_yaw(&self) -> f32 {
+        self.model_yaw
+    }
+
+    /// Rotate the model by `degrees` about the vertical (Y) axis through its bounding box
+    /// center.  Independent of the interactive camera.
+    pub fn set_model_yaw(&mut self, degrees: f32) {
+        self.model_yaw = degrees;
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.scene.camera.controller.projection()
+    }
+
+    /// Switch between perspective and orthographic projection.  Orthographic scales are world
+    /// units per pixel-art pixel, so they stay put when the render size changes and scale with
+    /// the pixel size.
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.scene.camera.controller.set_projection(projection);
+    }
+
+    /// World units per pixel at which the whole model fits the pixel-art output from any
+    /// direction
+    pub fn fit_units_per_pixel(&self) -> f32 {
+        let bbox = &self.scene.bbox;
+        (bbox.max - bbox.min).length() / self.pixelizer.target.sizef().min_element()
+    }
+
+    /// Look at the model's bounding box center from a preset direction, discarding interactive
+    /// camera changes.  Perspective cameras switch to an orthographic projection fitted to the
+    /// model, an existing orthographic scale is kept.
+    pub fn set_camera_preset(&mut self, preset: CameraPreset) {
+        let mid = self.scene.bbox.mid();
+        let distance = self.scene.bbox.longest_axis() * 1.5;
+        let (dir, up) = preset.eye_direction();
+        let camera = &mut self.scene.camera.controller;
+        camera.set_view(mid + dir * distance, mid, up);
+        if !camera.projection().is_orthographic() {
+            let units_per_pixel = self.fit_units_per_pixel();
+            self.scene
+                .camera
+                .controller
+                .set_projection(Projection::Orthographic { units_per_pixel });
+        }
+    }
+
+    pub fn pixel_snap(&self) -> bool {
+        self.scene.camera.controller.snap()
+    }
+
+    /// Snap the orthographic camera to whole pixel-art pixels so the low resolution grid
+    /// doesn't crawl over the model as the camera moves.  Display the pixel output shifted by
+    /// `snap_offset` to keep camera motion smooth.
+    pub fn set_pixel_snap(&mut self, snap: bool) {
+        self.scene.camera.controller.set_snap(snap);
+    }
+
+    /// Sub-pixel remainder of camera snapping in pixel-art pixels, x right and y down
+    pub fn snap_offset(&self) -> [f32; 2] {
+        self.scene.camera.controller.snap_offset().into()
+    }
+
+    pub fn snap_root_motion(&self) -> bool {
+        self.snap_root_motion
+    }
+
+    /// Move the model by less than a pixel so its root node always lands on the pixel-art grid.
+    /// Walk cycles with root motion then step by whole pixels instead of shimmering.
+    /// Orthographic projections only.
+    pub fn set_snap_root_motion(&mut self, snap: bool) {
+        self.snap_root_motion = snap;
+    }
+
+    /// Render the current pose once per turntable facing, turning the model rather than the
+    /// camera, along with the companion `maps` of each facing.  The model yaw is restored
+    /// afterwards.
+    pub fn render_turntable(
+        &mut self,
+        turntable: &Turntable,
+        maps: &[SpriteMap],
+    ) -> Vec<TurntableFrame> {
+        let model_yaw = self.model_yaw;
+        let frames = turntable
+            .facings()
+            .map(|(direction, yaw)| {
+                self.set_model_yaw(yaw);
+                TurntableFrame {
+                    direction,
+                    yaw,
+                    image: self.render_to_image(),
+                    maps: maps.iter().map(|&map| self.read_map_pixels(map)).collect(),
+                }
+            })
+            .collect();
+        self.model_yaw = model_yaw;
+        frames
+    }
+
+    /// Linear RGBA color the scene is cleared to
+    pub fn background(&self) -> [f32; 4] {
+        self.background
+    }
+
+    /// Set the clear color.  Fully transparent by default, so pixel output has an alpha
+    /// channel.  Pixelization thresholds alpha, partially transparent backgrounds end up either
+    /// opaque or fully transparent.
+    pub fn set_background(&mut self, color: [f32; 4]) {
+        self.background = color;
+    }
+
+    /// Animation clips of the loaded model
+    pub fn animations(&self) -> &[model::Animation] {
+        &self.scene.animations
+    }
+
+    /// Pose the model: node animation moves meshes, skins are updated on the GPU.  Use
+    /// `model::Model::rest_pose` to return to the bind pose.
+    pub fn set_pose(&mut self, pose: &model::Pose) {
+        if self.scene.set_pose(pose, &self.queue) {
+            self.regenerate_pipelines();
+        }
+    }
+
+    /// Return the model to its rest pose
+    pub fn reset_pose(&mut self) {
+        let pose = model::Pose::rest(&self.scene.nodes);
+        self.set_pose(&pose);
+    }
+
+    /// Pose the model with animation clip `animation` (an index into `animations`) at `time`
+    /// seconds.  Times past the end of the clip hold the last keyframe.  Unknown clips leave
+    /// the pose unchanged.
+    pub fn set_animation_time(&mut self, animation: usize, time: f32) {
+        let Some(clip) = self.scene.animations.get(animation) else {
+            return;
+        };
+        let mut pose = model::Pose::rest(&self.scene.nodes);
+        clip.sample(time, &mut pose);
+        self.set_pose(&pose);
+    }
+
+    pub fn outline(&self) -> &Outline {
+        &self.outline
+    }
+
+    /// Outline settings.  Thickness is in pixel-art output pixels and clamped to
+    /// [1, `Outline::MAX_THICKNESS`].
+    pub fn set_outline(&mut self, outline: Outline) {
+        if outline.edges != self.outline.edges {
+            self.hull.set_edges(outline.edges, &self.device);
+        }
+        self.outline = outline;
+    }
+
+    pub fn palette(&self) -> Option<&palette::Palette> {
+        self.quantizer.palette()
+    }
+
+    /// Quantize the pixelated output to `palette`.  Colors are matched in OKLab space.  `None`
+    /// disables quantization.
+    pub fn set_palette(&mut self, palette: Option<&palette::Palette>) {
+        self.quantizer.set_palette(palette, &self.queue);
+    }
+
+    pub fn dither(&self) -> (Dither, f32) {
+        self.quantizer.dither()
+    }
+
+    /// Dithering used when quantizing to the palette, with strength in [0,1].  Ordered modes
+    /// are anchored to the model's projected origin, so the pattern stays fixed on the sprite
+    /// across frames and camera pans.
+    pub fn set_dither(&mut self, dither: Dither, strength: f32) {
+        self.quantizer
+            .set_dither(dither, strength, &self.device, &self.queue);
+    }
+
+    /// Error diffusion needs compute shaders, on devices without them (eg, WebGL2) those modes
+    /// quantize undithered
+    pub fn supports_dither(&self, dither: Dither) -> bool {
+        !dither.is_error_diffusion() || quantize::Quantizer::supports_error_diffusion(&self.device)
+    }
+
+    pub fn shading(&self) -> ShadingModel {
+        self.shading
+    }
+
+    /// Switch the lighting model, rebuilding the mesh pipelines
+    pub fn set_shading(&mut self, shading: ShadingModel) {
+        if shading == self.shading {
+            return;
+        }
+        self.shading = shading;
+        self.regenerate_pipelines();
+    }
+
+    pub fn cull_mode(&self) -> CullMode {
+        self.cull_mode
+    }
+
+    pub fn set_cull_mode(&mut self, cull_mode: CullMode) {
+        if cull_mode == self.cull_mode {
+            return;
+        }
+        self.cull_mode = cull_mode;
+        self.regenerate_pipelines();
+    }
+
+    fn regenerate_pipelines(&mut self) {
+        self.render_pipelines = generate_pipelines(
+            &self.scene,
+            self.shading,
+            self.cull_mode,
+            &mut self.pipeline_cache,
+            &self.render_view,
+        );
+    }
+
+    pub fn cel_shading(&self) -> &CelShading {
+        &self.scene.light.controller.cel
+    }
+
+    pub fn set_cel_shading(&mut self, cel: CelShading) {
+        self.scene.light.controller.cel = cel;
+    }
+
+    /// Set the band color ramp used by `ShadingModel::CelRamp`
+    pub fn set_cel_ramp(&mut self, ramp: &image::RgbaImage) {
+        let layout = &self.scene.layouts.light;
+        self.scene
+            .light
+            .set_ramp(ramp, layout, &self.device, &self.queue);
+    }
+
+    /// Generate a palette of `count` colors from the most recent full resolution render.
+    /// `locked` colors are kept as-is and count towards `count`.
+    pub fn extract_palette(
+        &self,
+        method: palette::extract::Method,
+        count: usize,
+        locked: &[palette::Color],
+    ) -> palette::Palette {
+        let image = self.render_view.read_pixels(&self.device, &self.queue);
+        // The render holds premultiplied alpha, so partially covered pixels are un-premultiplied
+        // in linear space to keep them from darkening the palette
+        let colors: Vec<palette::Color> = image
+            .pixels()
+            .filter(|p| p[3] != 0)
+            .map(|p| {
+                let rgb = [p[0], p[1], p[2]];
+                if p[3] == 255 {
+                    return rgb;
+                }
+                let alpha = p[3] as f32 / 255.0;
+                palette::oklab::linear_to_srgb8(palette::oklab::srgb8_to_linear(rgb) / alpha)
+            })
+            .collect();
+        palette::extract::generate(method, "Generated", &colors, count, locked)
+    }
+
+    /// Copy the final pixel-art output of the most recent `render` back to the host.  Blocks
+    /// until the GPU is done.
+    pub fn read_pixels(&self) -> image::RgbaImage {
+        self.quantizer.target.read_pixels(&self.device, &self.queue)
+    }
+
+    /// Copy the full resolution render of the most recent `render` back to the host
+    pub fn read_render_pixels(&self) -> image::RgbaImage {
+        self.render_view.read_pixels(&self.device, &self.queue)
+    }
+
+    /// Produce and read back companion `map` of the most recent `render`, aligned pixel for
+    /// pixel with `read_pixels`
+    pub fn read_map_pixels(&self, map: SpriteMap) -> image::RgbaImage {
+        // The depth map spans the model's bounding sphere, so depths are comparable between
+        // frames and facings
+        let view_matrix = self.scene.camera.controller.view_matrix();
+        let bbox = &self.scene.bbox;
+        let center_depth = -view_matrix.transform_point3(bbox.mid()).z;
+        let radius = (bbox.max - bbox.min).length() * 0.5;
+
+        self.maps.render(
+            map,
+            center_depth - radius,
+            center_depth + radius,
+            &self.device,
+            &self.queue,
+        );
+        self.maps.target.read_pixels(&self.device, &self.queue)
+    }
+
+    /// Render and read back the pixel-art output.  Together with `headless::request_device`
+    /// this renders sprites without a window.
+    pub fn render_to_image(&self) -> image::RgbaImage {
+        self.render();
+        self.read_pixels()
+    }
+
+    /// Mesh indices in drawing order: opaque and alpha masked meshes first, then blended meshes
+    /// back to front by the view space depth of their centers
+    fn draw_order(&self, model_view: glam::Mat4) -> Vec<usize> {
+        let (mut blended, mut order): (Vec<usize>, Vec<usize>) = (0..self.scene.meshes.len())
+            .partition(|&idx| {
+                self.scene.material(&self.scene.meshes[idx]).alpha_mode == model::AlphaMode::Blend
+            });
+        let depth = |idx: usize| {
+            let mesh = &self.scene.meshes[idx];
+            (model_view * mesh.transform)
+                .transform_point3(mesh.center)
+                .z
+        };
+        // The camera looks down -Z, the farthest mesh has the smallest z
+        blended.sort_by(|&a, &b| depth(a).total_cmp(&depth(b)));
+        order.extend(blended);
+        order
+    }
+
     pub fn render(&self) {
         // Update camera uniforms
         let view_matrix = self.scene.camera.controller.view_matrix();
@@ -64,81 +528,122 @@ impl Renderer {
             0,
             bytemuck::cast_slice(&[view_proj]),
         );
+        self.queue.write_buffer(
+            &self.scene.camera.view_buffer,
+            0,
+            bytemuck::cast_slice(&[view_matrix]),
+        );
 
         self.scene.light.update_uniform(&self.queue);
 
-        for (mesh_idx, mesh) in self.scene.meshes.iter().enumerate() {
-            let mut encoder = self
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Render Encoder"),
-                });
-            {
-                self.queue.write_buffer(
-                    &self.scene.camera.view_proj_buffer,
-                    0,
-                    bytemuck::cast_slice(&[view_proj]),
-                );
-
-                let model_matrix = mesh.transform;
-                let normal_transform = generate_normal_transform(view_matrix, model_matrix);
-                self.queue.write_buffer(
-                    &mesh.normal_transform_buffer,
-                    0,
-                    bytemuck::cast_slice(&[normal_transform]),
-                );
-
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Render Pass"),
-                    color_attachments: &[
-                        // This is what @location(0) in the fragment shader targets
-                        Some(wgpu::RenderPassColorAttachment {
-                            view: &self.render_view.view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color {
-                                    r: 0.02,
-                                    g: 0.02,
-                                    b: 0.02,
-                                    a: 1.0,
-                                }),
-                                store: wgpu::StoreOp::Store,
-                            },
-                        }),
-                    ],
-                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                        view: &self.depth_texture.view,
-                        depth_ops: Some(wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(1.0),
+        // Spin the model about the vertical axis through its bounding box center
+        let mid = self.scene.bbox.mid();
+        let mut model_placement = glam::Mat4::from_translation(mid)
+            * glam::Mat4::from_rotation_y(self.model_yaw.to_radians())
+            * glam::Mat4::from_translation(-mid);
+        // Then nudge it so the root lands on the pixel-art grid
+        if self.snap_root_motion {
+            let root = model_placement.transform_point3(self.scene.root_position);
+            let snap = self.scene.camera.controller.snap_translation(root);
+            model_placement = glam::Mat4::from_translation(snap) * model_placement;
+        }
+
+        let draw_hull = self.outline.technique == OutlineTechnique::InvertedHull;
+        if draw_hull {
+            self.hull.update_uniform(
+                &self.outline,
+                self.quantizer.palette(),
+                self.pixelizer.target.size(),
+                &self.queue,
+            );
+        }
+
+        // Premultiplied, like everything drawn on top of it
+        let [r, g, b, a] = self.background.map(|c| c as f64);
+        let clear_color = wgpu::Color {
+            r: r * a,
+            g: g * a,
+            b: b * a,
+            a,
+        };
+
+        self.scene
+            .write_transforms(model_placement, view_matrix, &self.queue);
+
+        // All meshes go into one pass so they share the color and depth buffers
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+        {
+            let edge_attachments = self.edge_buffers.attachments();
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[
+                    // This is what @location(0) in the fragment shader targets
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &self.render_view.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(clear_color),
                             store: wgpu::StoreOp::Store,
-                        }),
-                        stencil_ops: None,
+                        },
                     }),
-                    timestamp_writes: None,
-                    occlusion_query_set: None,
-                });
-                render_pass.set_bind_group(0, &self.scene.camera.bind_group, &[]);
-                render_pass.set_bind_group(1, &mesh.bind_group, &[]);
-                render_pass.set_bind_group(2, &self.scene.light.bind_group, &[]);
-
-                render_pass.set_pipeline(&self.render_pipelines[mesh_idx]); // 2.
-                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-                if let Some(nbuff) = &mesh.normal_buffer {
-                    render_pass.set_vertex_buffer(1, nbuff.slice(..));
-                }
-                if let Some(tcbuff) = &mesh.texcoord_buffer {
-                    render_pass.set_vertex_buffer(2, tcbuff.slice(..));
+                    edge_attachments[0].clone(),
+                    edge_attachments[1].clone(),
+                    edge_attachments[2].clone(),
+                ],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_bind_group(0, &self.scene.camera.bind_group, &[]);
+
+            let order = self.draw_order(view_matrix * model_placement);
+            // Every hull goes first, so no hull covers a mesh drawn before it
+            if draw_hull {
+                for &mesh_idx in &order {
+                    let mesh = &self.scene.meshes[mesh_idx];
+                    mesh.set_bind_group(1, &mut render_pass);
+                    self.hull.draw(mesh, &mut render_pass);
                 }
+            }
+
+            for mesh_idx in order {
+                let mesh = &self.scene.meshes[mesh_idx];
+                mesh.set_bind_group(1, &mut render_pass);
+                render_pass.set_bind_group(2, &self.scene.light.bind_group, &[]);
+                let material_idx = mesh.material_id.unwrap_or(0) as usize;
+                render_pass.set_bind_group(3, &self.scene.materials[material_idx].bind_group, &[]);
+                render_pass.set_pipeline(&self.render_pipelines[mesh_idx]);
+                mesh.set_vertex_buffers(&mut render_pass);
                 if let Some(ibuff) = &mesh.index_buffer {
                     render_pass.set_index_buffer(ibuff.slice(..), wgpu::IndexFormat::Uint32);
                     render_pass.draw_indexed(0..mesh.num_triangles * 3, 0, 0..1);
                 } else {
                     render_pass.draw(0..mesh.num_triangles, 0..1);
                 }
-                //render_pass.draw(0..3, 0..1);
             }
-            self.queue.submit(std::iter::once(encoder.finish()));
         }
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        self.pixelizer.render(&self.device, &self.queue);
+        self.outliner.render(
+            &self.outline,
+            self.quantizer.palette(),
+            &self.device,
+            &self.queue,
+        );
+        self.quantizer
+            .render(self.dither_origin(view_proj), &self.device, &self.queue);
     }
 
     pub fn handle_event(&mut self, event: event::Event) {
@@ -169,6 +674,14 @@ impl Renderer {
         }
     }
 
+    /// Pixel-art raster position of the model's bounding box center
+    fn dither_origin(&self, view_proj: glam::Mat4) -> glam::IVec2 {
+        let ndc = view_proj.project_point3(self.scene.bbox.mid());
+        let size = self.pixelizer.target.sizef();
+        let raster = glam::Vec2::new(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5) * size;
+        raster.round().as_ivec2()
+    }
+
     fn raster_to_ndc(&self, r: glam::Vec2) -> glam::Vec2 {
         let size = self.render_view.sizef();
         // invert y
@@ -186,6 +699,7 @@ impl Renderer {
 /// Texture dims are fixed at creation time.
 struct RenderView {
     desc: wgpu::TextureDescriptor<'static>,
+    texture: wgpu::Texture,
     view: wgpu::TextureView,
 }
 impl RenderView {
@@ -194,6 +708,16 @@ impl RenderView {
 
     /// Create WGPU texture for render target
     fn new(size: &[u32; 2], device: &wgpu::Device) -> Self {
+        Self::with_format(size, wgpu::TextureFormat::Rgba8UnormSrgb, device)
+    }
+
+    /// Render target with an RGBA8 `format` other than the one egui needs, for data that isn't
+    /// displayed directly
+    fn with_format(size: &[u32; 2], format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+        let view_formats = match format {
+            wgpu::TextureFormat::Rgba8UnormSrgb => RenderView::VIEW_FORMATS,
+            _ => &[],
+        };
         let desc = wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
                 width: size[0],
@@ -203,19 +727,36 @@ impl RenderView {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            view_formats: RenderView::VIEW_FORMATS,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            format,
+            view_formats,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
             label: None,
         };
         let texture = device.create_texture(&desc);
         let view = texture.create_view(&Default::default());
 
-        Self { desc, view }
+        Self {
+            desc,
+            texture,
+            view,
+        }
+    }
+
+    /// Copy the texture contents back to the host.  Pixels are sRGB encoded unless the view was
+    /// created `with_format` a linear format.
+    fn read_pixels(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> image::RgbaImage {
+        let size = self.size();
+        image::RgbaImage::from_raw(
+            size.x,
+            size.y,
+            texture::read_rgba8(&self.texture, device, queue),
+        )
+        .expect("Readback size matches the texture")
     }
 
     /// return render texture dimensions (width, height)
-    #[allow(unused)]
     fn size(&self) -> glam::UVec2 {
         glam::UVec2::new(self.desc.size.width, self.desc.size.height)
     }
@@ -226,141 +767,47 @@ impl RenderView {
     }
 }
 
+/// Pipeline of each mesh, shared between meshes that need the same one
 fn generate_pipelines(
     scene: &Scene,
-    shader_cache: &mut shader::Cache,
+    shading: ShadingModel,
+    cull_mode: CullMode,
+    pipeline_cache: &mut pipeline::Cache,
     render_view: &RenderView,
-    device: &wgpu::Device,
 ) -> Vec<wgpu::RenderPipeline> {
-    let mut pipelines = Vec::new();
-
-    for mesh in &scene.meshes {
-        // build render pipeline layout
-        let bind_group_layouts = [
-            &WGPUCamera::bind_group_layout(device),
-            &WGPUMesh::bind_group_layout(device),
-            &WGPULight::bind_group_layout(device),
-        ];
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &bind_group_layouts,
-                push_constant_ranges: &[],
-            });
-
-        // build pipeline
-        let mut shader_spec = shader::Specification::default();
-
-        let mut vertex_buffer_layouts = vec![wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<glam::Vec3>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[wgpu::VertexAttribute {
-                // Positions
-                offset: 0,
-                shader_location: 0,
-                format: wgpu::VertexFormat::Float32x3,
-            }],
-        }];
-
-        // TODO:: create abstraction for VertexAttribute
-        if mesh.normal_buffer.is_some() {
-            println!("setting normal buffer");
-            shader_spec.has_normals = true;
-            vertex_buffer_layouts.push(wgpu::VertexBufferLayout {
-                array_stride: std::mem::size_of::<glam::Vec3>() as wgpu::BufferAddress,
-                step_mode: wgpu::VertexStepMode::Vertex,
-                attributes: &[wgpu::VertexAttribute {
-                    // Normals
-                    offset: 0,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                }],
-            });
-        }
-
-        if mesh.texcoord_buffer.is_some() {
-            println!("setting texcoord buffer");
-            shader_spec.has_texcoords = true;
-            vertex_buffer_layouts.push(wgpu::VertexBufferLayout {
-                array_stride: std::mem::size_of::<glam::Vec2>() as wgpu::BufferAddress,
-                step_mode: wgpu::VertexStepMode::Vertex,
-                attributes: &[wgpu::VertexAttribute {
-                    // Normals
-                    offset: 0,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x2,
-                }],
-            });
-        }
-
-        let (vert_module, frag_module) = shader_cache.get_modules(shader_spec);
-
-        let vertex_state = wgpu::VertexState {
-            module: vert_module,
-            entry_point: Some("main"),
-            buffers: &vertex_buffer_layouts,
-            compilation_options: Default::default(),
-        };
-
-        let fragment_state = wgpu::FragmentState {
-            module: frag_module,
-            entry_point: Some("main"),
-            targets: &[Some(wgpu::ColorTargetState {
+    scene
+        .meshes
+        .iter()
+        .map(|mesh| {
+            let material = scene.material(mesh);
+            let spec = shader::Specification {
+                has_normals: mesh.normal_buffer.is_some(),
+                has_tangents: mesh.tangent_buffer.is_some(),
+                has_texcoords: mesh.texcoord_buffer.is_some(),
+                has_colors: mesh.color_buffer.is_some(),
+                has_joints: mesh.has_joints(),
+                has_morph_targets: mesh.has_morph_targets(),
+                shading,
+                alpha_mode: material.alpha_mode,
+            };
+            let key = pipeline::Key {
+                spec,
+                front_face: if mesh.mirrored {
+                    wgpu::FrontFace::Cw
+                } else {
+                    wgpu::FrontFace::Ccw
+                },
+                cull_mode: match cull_mode {
+                    CullMode::Material if material.double_sided => None,
+                    CullMode::Material | CullMode::Back => Some(wgpu::Face::Back),
+                    CullMode::Front => Some(wgpu::Face::Front),
+                    CullMode::None => None,
+                },
                 format: render_view.desc.format,
-                blend: Some(wgpu::BlendState {
-                    color: wgpu::BlendComponent::REPLACE,
-                    alpha: wgpu::BlendComponent::REPLACE,
-                }),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-            compilation_options: Default::default(),
-        };
-
-        let primitive_state = wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            polygon_mode: wgpu::PolygonMode::Fill,
-            // Requires Features::DEPTH_CLIP_CONTROL
-            unclipped_depth: false,
-            // Requires Features::CONSERVATIVE_RASTERIZATION
-            conservative: false,
-        };
-
-        let multisample_state = wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        };
-
-        let depth_stencil = Some(wgpu::DepthStencilState {
-            format: texture::Texture::DEPTH_FORMAT,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: vertex_state,
-            fragment: Some(fragment_state),
-            primitive: primitive_state,
-            depth_stencil,
-            multisample: multisample_state,
-            // If the pipeline will be used with a multiview render pass, this
-            // indicates how many array layers the attachments will have.
-            multiview: None,
-            // Useful for optimizing shader compilation on Android
-            cache: None,
-        });
-
-        pipelines.push(render_pipeline);
-    }
-
-    pipelines
+            };
+            pipeline_cache.get_pipeline(key).clone()
+        })
+        .collect()
 }
 
 fn generate_normal_transform(view_matrix: glam::Mat4, model_matrix: glam::Mat4) -> glam::Mat4 {
@@ -25,4 +25,69 @@ impl Default for UniformData {
 #[derive(Default)]
 pub struct Light {
     pub uniform_data: UniformData,
+    pub cel: CelShading,
+}
+
+/// Parameters for the cel shading model.  Diffuse lighting is quantized into `bands` flat levels
+/// split at `thresholds` (ascending N.L values, one fewer than `bands`).  A single hard-edged
+/// specular band is added where the Blinn-Phong term exceeds `specular_threshold`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CelShading {
+    pub bands: u32,
+    pub thresholds: Vec<f32>,
+    pub specular_threshold: f32,
+    pub specular_shininess: f32,
+    /// Zero disables the specular band
+    pub specular_intensity: f32,
+}
+
+impl CelShading {
+    pub const MAX_BANDS: u32 = 8;
+
+    /// `bands` levels with evenly spaced thresholds
+    pub fn with_bands(bands: u32) -> Self {
+        let bands = bands.clamp(2, Self::MAX_BANDS);
+        Self {
+            bands,
+            thresholds: (1..bands).map(|i| i as f32 / bands as f32).collect(),
+            ..Default::default()
+        }
+    }
+
+    pub fn uniform_data(&self) -> CelUniformData {
+        let bands = self.bands.clamp(2, Self::MAX_BANDS);
+        let mut thresholds = [[1.0f32; 4]; 2];
+        for (i, t) in self.thresholds.iter().take(bands as usize - 1).enumerate() {
+            thresholds[i / 4][i % 4] = *t;
+        }
+        CelUniformData {
+            bands,
+            specular_threshold: self.specular_threshold,
+            specular_shininess: self.specular_shininess,
+            specular_intensity: self.specular_intensity,
+            thresholds,
+        }
+    }
+}
+
+impl Default for CelShading {
+    fn default() -> Self {
+        Self {
+            bands: 3,
+            thresholds: vec![0.2, 0.6],
+            specular_threshold: 0.5,
+            specular_shininess: 32.0,
+            specular_intensity: 0.4,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CelUniformData {
+    bands: u32,
+    specular_threshold: f32,
+    specular_shininess: f32,
+    specular_intensity: f32,
+    thresholds: [[f32; 4]; 2],
 }
@@ -0,0 +1,254 @@
+use crate::fullscreen;
+use crate::outline::EdgeBuffers;
+use crate::pixelize::Pixelizer;
+use crate::RenderView;
+
+/// Companion images of the pixel-art sprite, aligned with it pixel for pixel
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SpriteMap {
+    /// View space normal, xyz mapped from [-1,1] to RGB.  X right, Y up, Z towards the viewer.
+    Normal = 0,
+    /// Linear view depth across the model's bounding sphere, white nearest
+    Depth = 1,
+    /// Unlit base color
+    Albedo = 2,
+    /// Flat color per mesh primitive
+    MeshId = 3,
+    /// Flat color per material
+    MaterialId = 4,
+}
+
+impl SpriteMap {
+    pub const ALL: [SpriteMap; 5] = [
+        SpriteMap::Normal,
+        SpriteMap::Depth,
+        SpriteMap::Albedo,
+        SpriteMap::MeshId,
+        SpriteMap::MaterialId,
+    ];
+
+    /// Lower case name, also used as the file name suffix of exported maps
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpriteMap::Normal => "normal",
+            SpriteMap::Depth => "depth",
+            SpriteMap::Albedo => "albedo",
+            SpriteMap::MeshId => "mesh-id",
+            SpriteMap::MaterialId => "material-id",
+        }
+    }
+}
+
+impl std::str::FromStr for SpriteMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SpriteMap::ALL
+            .into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = SpriteMap::ALL.iter().map(|m| m.name()).collect();
+                format!("unknown map '{}', expected {}", s, names.join(", "))
+            })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct UniformData {
+    map: u32,
+    depth_near: f32,
+    depth_far: f32,
+    _pad: u32,
+}
+
+/// Image-space pass producing a `SpriteMap` from the full resolution edge buffers, sampled at
+/// the source texels the pixelizer chose for the final sprite.  Output is plain RGBA8, not sRGB
+/// encoded, so normals and depth keep their values.
+pub struct MapRenderer {
+    pub target: RenderView,
+
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl MapRenderer {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+    /// `sprite` is the final pixel-art image, at the pixelizer's resolution
+    pub fn new(
+        pixelizer: &Pixelizer,
+        sprite: &RenderView,
+        edge_buffers: &EdgeBuffers,
+        device: &wgpu::Device,
+    ) -> Self {
+        let target = RenderView::with_format(&sprite.size().into(), Self::FORMAT, device);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Map Uniform Buffer"),
+            size: std::mem::size_of::<UniformData>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sampler = fullscreen::nearest_sampler(device);
+        let bind_group_layout = Self::bind_group_layout(device);
+        let bind_group = Self::bind_group(
+            &bind_group_layout,
+            pixelizer,
+            sprite,
+            edge_buffers,
+            &sampler,
+            &uniform_buffer,
+            device,
+        );
+
+        let pipeline = fullscreen::create_pipeline(
+            "Map Pipeline",
+            include_str!("../shader/maps.glsl"),
+            &[&bind_group_layout],
+            Self::FORMAT,
+            device,
+        );
+
+        Self {
+            target,
+            uniform_buffer,
+            sampler,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Rebind after the pixelizer and sprite targets were recreated
+    pub fn resize(
+        &mut self,
+        pixelizer: &Pixelizer,
+        sprite: &RenderView,
+        edge_buffers: &EdgeBuffers,
+        device: &wgpu::Device,
+    ) {
+        self.target = RenderView::with_format(&sprite.size().into(), Self::FORMAT, device);
+        self.bind_group = Self::bind_group(
+            &self.bind_group_layout,
+            pixelizer,
+            sprite,
+            edge_buffers,
+            &self.sampler,
+            &self.uniform_buffer,
+            device,
+        );
+    }
+
+    /// Draw `map` into `target`.  View depths in [`depth_near`, `depth_far`] span the depth
+    /// map's range.
+    pub fn render(
+        &self,
+        map: SpriteMap,
+        depth_near: f32,
+        depth_far: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let uniform_data = UniformData {
+            map: map as u32,
+            depth_near,
+            depth_far,
+            _pad: 0,
+        };
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform_data]),
+        );
+
+        fullscreen::draw(
+            "Map Pass",
+            &self.pipeline,
+            &self.bind_group,
+            &self.target.view,
+            device,
+            queue,
+        );
+    }
+
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let float = wgpu::TextureSampleType::Float { filterable: false };
+        let uint = wgpu::TextureSampleType::Uint;
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture(0, uint),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture(3, float),
+                texture(4, float),
+                texture(5, uint),
+                texture(6, float),
+            ],
+            label: Some("Map BindGroupLayout"),
+        })
+    }
+
+    fn bind_group(
+        layout: &wgpu::BindGroupLayout,
+        pixelizer: &Pixelizer,
+        sprite: &RenderView,
+        edge_buffers: &EdgeBuffers,
+        sampler: &wgpu::Sampler,
+        uniform_buffer: &wgpu::Buffer,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroup {
+        let texture = |binding, view| wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(view),
+        };
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                texture(0, &pixelizer.source_texels),
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                texture(3, &sprite.view),
+                texture(4, &edge_buffers.normal_depth),
+                texture(5, &edge_buffers.object_id),
+                texture(6, &edge_buffers.albedo),
+            ],
+            label: Some("Map BindGroup"),
+        })
+    }
+}
@@ -0,0 +1,659 @@
+use crate::fullscreen;
+use crate::scene::{Layouts, WGPUMesh};
+use crate::texture;
+use crate::RenderView;
+
+/// How outlines are produced
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Technique {
+    #[default]
+    None,
+    /// Edge detection on the depth, normal and object-id buffers, run at pixel-art resolution
+    ScreenSpace,
+    /// Back faces of each mesh extruded along their normals, drawn behind the mesh.  Meshes
+    /// without normals get no hull.
+    InvertedHull,
+}
+
+impl Technique {
+    pub const ALL: [Technique; 3] = [
+        Technique::None,
+        Technique::ScreenSpace,
+        Technique::InvertedHull,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Technique::None => "None",
+            Technique::ScreenSpace => "Screen space",
+            Technique::InvertedHull => "Inverted hull",
+        }
+    }
+}
+
+/// Which edges get lines
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Edges {
+    /// Silhouette against the background only
+    #[default]
+    Outer,
+    /// Silhouette plus inner depth, object and crease edges
+    All,
+}
+
+impl Edges {
+    pub const ALL: [Edges; 2] = [Edges::Outer, Edges::All];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Edges::Outer => "Outer",
+            Edges::All => "Outer + inner",
+        }
+    }
+}
+
+/// Color of outline pixels
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum LineColor {
+    /// Fixed sRGB color
+    Fixed(palette::Color),
+    /// Entry of the active palette, black while no palette is set or the index is out of range
+    PaletteIndex(usize),
+    /// Color of the outlined surface scaled by a factor in (0,1].  The inverted hull has no
+    /// surface color to sample and uses white as the base.
+    Darken(f32),
+}
+
+impl Default for LineColor {
+    fn default() -> Self {
+        LineColor::Fixed([0, 0, 0])
+    }
+}
+
+/// Outline settings
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Outline {
+    pub technique: Technique,
+    pub edges: Edges,
+    pub color: LineColor,
+    /// Line width in pixel-art output pixels
+    pub thickness: u32,
+    /// Relative view depth difference treated as an inner edge (screen space only)
+    pub depth_threshold: f32,
+    /// Angle in degrees between neighbouring normals treated as a crease (screen space only)
+    pub crease_angle: f32,
+}
+
+impl Outline {
+    pub const MAX_THICKNESS: u32 = 4;
+
+    /// Line color in linear RGB, or `None` for `LineColor::Darken`
+    fn fixed_color(&self, palette: Option<&palette::Palette>) -> Option<glam::Vec3> {
+        let srgb = match self.color {
+            LineColor::Fixed(c) => c,
+            LineColor::PaletteIndex(idx) => palette
+                .and_then(|p| p.colors.get(idx).copied())
+                .unwrap_or([0, 0, 0]),
+            LineColor::Darken(_) => return None,
+        };
+        Some(palette::oklab::srgb8_to_linear(srgb))
+    }
+
+    fn darken(&self) -> f32 {
+        match self.color {
+            LineColor::Darken(f) => f.clamp(f32::EPSILON, 1.0),
+            _ => 0.0,
+        }
+    }
+
+    fn thickness(&self) -> u32 {
+        self.thickness.clamp(1, Self::MAX_THICKNESS)
+    }
+}
+
+impl Default for Outline {
+    fn default() -> Self {
+        Self {
+            technique: Technique::None,
+            edges: Edges::Outer,
+            color: LineColor::default(),
+            thickness: 1,
+            depth_threshold: 0.05,
+            crease_angle: 60.0,
+        }
+    }
+}
+
+/// Full resolution edge detection inputs written by the main render pass alongside color:
+/// view space normal with linear view depth, and per-mesh object ids (material ids in the
+/// second channel).  Also holds the unlit albedo for companion maps.  All are zero where nothing
+/// was drawn.
+pub struct EdgeBuffers {
+    pub normal_depth: wgpu::TextureView,
+    pub object_id: wgpu::TextureView,
+    pub albedo: wgpu::TextureView,
+}
+
+impl EdgeBuffers {
+    pub const NORMAL_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    pub const OBJECT_ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg32Uint;
+    pub const ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    pub fn new(size: &[u32; 2], device: &wgpu::Device) -> Self {
+        let create_view = |label, format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size[0],
+                        height: size[1],
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default())
+        };
+
+        Self {
+            normal_depth: create_view("Normal Depth Texture", Self::NORMAL_DEPTH_FORMAT),
+            object_id: create_view("Object Id Texture", Self::OBJECT_ID_FORMAT),
+            albedo: create_view("Albedo Texture", Self::ALBEDO_FORMAT),
+        }
+    }
+
+    /// Color targets following the main color target in the mesh pipelines
+    pub fn targets() -> [Option<wgpu::ColorTargetState>; 3] {
+        [
+            Some(Self::NORMAL_DEPTH_FORMAT.into()),
+            Some(Self::OBJECT_ID_FORMAT.into()),
+            Some(Self::ALBEDO_FORMAT.into()),
+        ]
+    }
+
+    /// Attachments matching `targets`, cleared to background
+    pub fn attachments(&self) -> [Option<wgpu::RenderPassColorAttachment<'_>>; 3] {
+        [&self.normal_depth, &self.object_id, &self.albedo].map(|view| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct OutlineUniformData {
+    line_color: [f32; 4],
+    src_size: [u32; 2],
+    dst_size: [u32; 2],
+    edges: u32,
+    thickness: i32,
+    darken: f32,
+    depth_threshold: f32,
+    normal_threshold: f32,
+    _pad: [u32; 3],
+}
+
+/// Image-space pass drawing screen-space outlines onto the pixelated image.  A pass-through
+/// unless the technique is `Technique::ScreenSpace`.
+pub struct Outliner {
+    src_size: glam::UVec2,
+    pub target: RenderView,
+
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    edge_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Outliner {
+    /// `source` is the pixelated image, `edge_buffers` the full resolution buffers of size
+    /// `src_size`
+    pub fn new(
+        source: &RenderView,
+        edge_buffers: &EdgeBuffers,
+        src_size: glam::UVec2,
+        device: &wgpu::Device,
+    ) -> Self {
+        let target = RenderView::new(&source.size().into(), device);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Outline Uniform Buffer"),
+            size: std::mem::size_of::<OutlineUniformData>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sampler = fullscreen::nearest_sampler(device);
+        let bind_group_layout = fullscreen::bind_group_layout("Outline BindGroupLayout", device);
+        let bind_group = fullscreen::bind_group(
+            "Outline BindGroup",
+            &bind_group_layout,
+            &source.view,
+            &sampler,
+            &uniform_buffer,
+            device,
+        );
+
+        let edge_bind_group_layout = Self::edge_bind_group_layout(device);
+        let edge_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &edge_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&edge_buffers.normal_depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&edge_buffers.object_id),
+                },
+            ],
+            label: Some("Outline Edge BindGroup"),
+        });
+
+        let pipeline = fullscreen::create_pipeline(
+            "Outline Pipeline",
+            include_str!("../shader/outline.glsl"),
+            &[&bind_group_layout, &edge_bind_group_layout],
+            target.desc.format,
+            device,
+        );
+
+        Self {
+            src_size,
+            target,
+            uniform_buffer,
+            sampler,
+            bind_group_layout,
+            bind_group,
+            edge_bind_group,
+            pipeline,
+        }
+    }
+
+    /// Rebind to a new source view, recreating the target to match its size
+    pub fn resize(&mut self, source: &RenderView, device: &wgpu::Device) {
+        self.target = RenderView::new(&source.size().into(), device);
+        self.bind_group = fullscreen::bind_group(
+            "Outline BindGroup",
+            &self.bind_group_layout,
+            &source.view,
+            &self.sampler,
+            &self.uniform_buffer,
+            device,
+        );
+    }
+
+    pub fn render(
+        &self,
+        outline: &Outline,
+        palette: Option<&palette::Palette>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let edges = match (outline.technique, outline.edges) {
+            (Technique::ScreenSpace, Edges::Outer) => 1,
+            (Technique::ScreenSpace, Edges::All) => 2,
+            _ => 0,
+        };
+        let uniform_data = OutlineUniformData {
+            line_color: outline
+                .fixed_color(palette)
+                .unwrap_or_default()
+                .extend(1.0)
+                .into(),
+            src_size: self.src_size.into(),
+            dst_size: self.target.size().into(),
+            edges,
+            thickness: outline.thickness() as i32,
+            darken: outline.darken(),
+            depth_threshold: outline.depth_threshold,
+            normal_threshold: outline.crease_angle.to_radians().cos(),
+            _pad: [0; 3],
+        };
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform_data]),
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Outline Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Outline Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.edge_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    fn edge_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0, wgpu::TextureSampleType::Float { filterable: false }),
+                texture_entry(1, wgpu::TextureSampleType::Uint),
+            ],
+            label: Some("Outline Edge BindGroupLayout"),
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct HullUniformData {
+    line_color: [f32; 4],
+    pixel_size: [f32; 2],
+    thickness: f32,
+    _pad: f32,
+}
+
+/// Inverted-hull outline geometry, drawn in the main render pass for every mesh with normals
+/// before any of the meshes
+pub struct Hull {
+    uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    /// One pipeline per vertex deformation variant, see `Hull::variant`
+    pub pipelines: Vec<wgpu::RenderPipeline>,
+}
+
+impl Hull {
+    /// `layouts` are the mesh pass layouts, the hull shares the camera and mesh bind groups
+    pub fn new(edges: Edges, layouts: &Layouts, device: &wgpu::Device) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Hull Uniform Buffer"),
+            size: std::mem::size_of::<HullUniformData>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = Self::bind_group_layout(device);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("Hull BindGroup"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Hull Pipeline Layout"),
+            bind_group_layouts: &[&layouts.camera, &layouts.mesh, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            uniform_buffer,
+            bind_group,
+            pipelines: Self::create_pipelines(edges, &pipeline_layout, device),
+            pipeline_layout,
+        }
+    }
+
+    /// Rebuild the pipelines, the depth state depends on which edges are drawn
+    pub fn set_edges(&mut self, edges: Edges, device: &wgpu::Device) {
+        self.pipelines = Self::create_pipelines(edges, &self.pipeline_layout, device);
+    }
+
+    /// Pipeline index for a mesh: bit 0 set for skinning, bit 1 for morph targets, bit 2 for
+    /// mirrored meshes
+    fn variant(skinned: bool, morphed: bool, mirrored: bool) -> usize {
+        skinned as usize | (morphed as usize) << 1 | (mirrored as usize) << 2
+    }
+
+    fn create_pipelines(
+        edges: Edges,
+        layout: &wgpu::PipelineLayout,
+        device: &wgpu::Device,
+    ) -> Vec<wgpu::RenderPipeline> {
+        (0..8)
+            .map(|variant| {
+                let skinned = variant & 1 != 0;
+                let morphed = variant & 2 != 0;
+                let mirrored = variant & 4 != 0;
+                debug_assert_eq!(Self::variant(skinned, morphed, mirrored), variant);
+                Self::create_pipeline(edges, skinned, morphed, mirrored, layout, device)
+            })
+            .collect()
+    }
+
+    /// Draw the hull of `mesh`, which must have normals
+    pub fn draw(&self, mesh: &WGPUMesh, render_pass: &mut wgpu::RenderPass<'_>) {
+        let Some(normal_buffer) = &mesh.normal_buffer else {
+            return;
+        };
+        render_pass.set_bind_group(2, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, normal_buffer.slice(..));
+        let skinned = match (&mesh.joints_buffer, &mesh.weights_buffer) {
+            (Some(joints), Some(weights)) => {
+                render_pass.set_vertex_buffer(2, joints.slice(..));
+                render_pass.set_vertex_buffer(3, weights.slice(..));
+                true
+            }
+            _ => false,
+        };
+        let variant = Self::variant(skinned, mesh.has_morph_targets(), mesh.mirrored);
+        render_pass.set_pipeline(&self.pipelines[variant]);
+        if let Some(ibuff) = &mesh.index_buffer {
+            render_pass.set_index_buffer(ibuff.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..mesh.num_triangles * 3, 0, 0..1);
+        } else {
+            render_pass.draw(0..mesh.num_triangles, 0..1);
+        }
+    }
+
+    pub fn update_uniform(
+        &self,
+        outline: &Outline,
+        palette: Option<&palette::Palette>,
+        pixel_size: glam::UVec2,
+        queue: &wgpu::Queue,
+    ) {
+        let line_color = outline
+            .fixed_color(palette)
+            .unwrap_or(glam::Vec3::splat(outline.darken()));
+        let uniform_data = HullUniformData {
+            line_color: line_color.extend(1.0).into(),
+            pixel_size: pixel_size.as_vec2().into(),
+            thickness: outline.thickness() as f32,
+            _pad: 0.0,
+        };
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform_data]),
+        );
+    }
+
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("Hull BindGroupLayout"),
+        })
+    }
+
+    fn create_pipeline(
+        edges: Edges,
+        skinned: bool,
+        morphed: bool,
+        mirrored: bool,
+        layout: &wgpu::PipelineLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::RenderPipeline {
+        let mut defines = wgpu::naga::FastHashMap::default();
+        if skinned {
+            defines.insert("HAS_JOINTS".to_string(), "1".to_string());
+        }
+        if morphed {
+            defines.insert("HAS_MORPH_TARGETS".to_string(), "1".to_string());
+        }
+        let vert_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("HullVertShader"),
+            source: wgpu::ShaderSource::Glsl {
+                shader: include_str!("../shader/hull_vert.glsl").into(),
+                stage: wgpu::naga::ShaderStage::Vertex,
+                defines,
+            },
+        });
+        let frag_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("HullFragShader"),
+            source: wgpu::ShaderSource::Glsl {
+                shader: include_str!("../shader/hull_frag.glsl").into(),
+                stage: wgpu::naga::ShaderStage::Fragment,
+                defines: Default::default(),
+            },
+        });
+
+        let mut vertex_buffer_layouts = vec![
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<glam::Vec3>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &[wgpu::VertexAttribute {
+                    // Positions
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                }],
+            },
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<glam::Vec3>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &[wgpu::VertexAttribute {
+                    // Normals
+                    offset: 0,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                }],
+            },
+        ];
+        if skinned {
+            vertex_buffer_layouts.extend([
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<glam::U16Vec4>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttribute {
+                        // Joints
+                        offset: 0,
+                        shader_location: 2,
+                        format: wgpu::VertexFormat::Uint16x4,
+                    }],
+                },
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<glam::Vec4>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttribute {
+                        // Weights
+                        offset: 0,
+                        shader_location: 3,
+                        format: wgpu::VertexFormat::Float32x4,
+                    }],
+                },
+            ]);
+        }
+
+        // Outer-only hulls ignore depth and are all drawn before the meshes, which then cover
+        // everything but the silhouette rim
+        let (depth_write_enabled, depth_compare) = match edges {
+            Edges::Outer => (false, wgpu::CompareFunction::Always),
+            Edges::All => (true, wgpu::CompareFunction::Less),
+        };
+
+        let [normal_depth_target, object_id_target, albedo_target] = EdgeBuffers::targets();
+        let targets = [
+            Some(wgpu::ColorTargetState {
+                format: RenderView::VIEW_FORMATS[0],
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            }),
+            normal_depth_target,
+            object_id_target,
+            albedo_target,
+        ];
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Hull Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &vert_module,
+                entry_point: Some("main"),
+                buffers: &vertex_buffer_layouts,
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &frag_module,
+                entry_point: Some("main"),
+                targets: &targets,
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                front_face: if mirrored {
+                    wgpu::FrontFace::Cw
+                } else {
+                    wgpu::FrontFace::Ccw
+                },
+                cull_mode: Some(wgpu::Face::Front),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+}
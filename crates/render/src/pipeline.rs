@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use crate::outline;
+use crate::scene::Layouts;
+use crate::shader;
+use crate::texture;
+
+/// Everything a mesh pipeline depends on.  Meshes with equal keys share one pipeline.
+#[derive(PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Key {
+    /// Shaders.  The attribute flags also fix the vertex buffer layout and the alpha mode fixes
+    /// blending and depth writes.
+    pub spec: shader::Specification,
+    pub front_face: wgpu::FrontFace,
+    pub cull_mode: Option<wgpu::Face>,
+    /// Format of the color target, the edge buffer targets are fixed
+    pub format: wgpu::TextureFormat,
+}
+
+/// Mesh render pipelines by `Key`, sharing one pipeline layout
+pub struct Cache {
+    device: wgpu::Device,
+    layout: wgpu::PipelineLayout,
+    shaders: shader::Cache,
+    pipelines: HashMap<Key, wgpu::RenderPipeline>,
+}
+
+impl Cache {
+    pub fn new(layouts: &Layouts, device: &wgpu::Device) -> Self {
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[
+                &layouts.camera,
+                &layouts.mesh,
+                &layouts.light,
+                &layouts.material,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            device: device.clone(),
+            layout,
+            shaders: shader::Cache::new(device),
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Pipeline for `key`, built on first use
+    pub fn get_pipeline(&mut self, key: Key) -> &wgpu::RenderPipeline {
+        let Self {
+            device,
+            layout,
+            shaders,
+            pipelines,
+        } = self;
+        pipelines
+            .entry(key)
+            .or_insert_with(|| Self::create_pipeline(key, layout, shaders, device))
+    }
+
+    fn create_pipeline(
+        key: Key,
+        layout: &wgpu::PipelineLayout,
+        shaders: &mut shader::Cache,
+        device: &wgpu::Device,
+    ) -> wgpu::RenderPipeline {
+        let spec = key.spec;
+
+        // One buffer per attribute, in the slot order of `WGPUMesh::set_vertex_buffers`
+        let attributes: Vec<[wgpu::VertexAttribute; 1]> = [
+            (0, wgpu::VertexFormat::Float32x3, true),
+            (1, wgpu::VertexFormat::Float32x3, spec.has_normals),
+            (2, wgpu::VertexFormat::Float32x2, spec.has_texcoords),
+            // Joints and weights
+            (3, wgpu::VertexFormat::Uint16x4, spec.has_joints),
+            (4, wgpu::VertexFormat::Float32x4, spec.has_joints),
+            (5, wgpu::VertexFormat::Float32x4, spec.has_tangents),
+            (6, wgpu::VertexFormat::Float32x4, spec.has_colors),
+        ]
+        .into_iter()
+        .filter(|&(_, _, present)| present)
+        .map(|(shader_location, format, _)| {
+            [wgpu::VertexAttribute {
+                offset: 0,
+                shader_location,
+                format,
+            }]
+        })
+        .collect();
+        let vertex_buffer_layouts: Vec<wgpu::VertexBufferLayout> = attributes
+            .iter()
+            .map(|attributes| wgpu::VertexBufferLayout {
+                array_stride: attributes[0].format.size(),
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes,
+            })
+            .collect();
+
+        let (vert_module, frag_module) = shaders.get_modules(spec);
+
+        let vertex_state = wgpu::VertexState {
+            module: vert_module,
+            entry_point: Some("main"),
+            buffers: &vertex_buffer_layouts,
+            compilation_options: Default::default(),
+        };
+
+        let edge_targets = outline::EdgeBuffers::targets();
+        let fragment_state = wgpu::FragmentState {
+            module: frag_module,
+            entry_point: Some("main"),
+            targets: &[
+                Some(wgpu::ColorTargetState {
+                    format: key.format,
+                    blend: Some(match spec.alpha_mode {
+                        model::AlphaMode::Blend => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+                        _ => wgpu::BlendState::REPLACE,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                edge_targets[0].clone(),
+                edge_targets[1].clone(),
+                edge_targets[2].clone(),
+            ],
+            compilation_options: Default::default(),
+        };
+
+        let primitive_state = wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: key.front_face,
+            cull_mode: key.cull_mode,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            // Requires Features::DEPTH_CLIP_CONTROL
+            unclipped_depth: false,
+            // Requires Features::CONSERVATIVE_RASTERIZATION
+            conservative: false,
+        };
+
+        let multisample_state = wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        };
+
+        let depth_stencil = Some(wgpu::DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            // Blended surfaces don't hide what is drawn behind them later
+            depth_write_enabled: spec.alpha_mode != model::AlphaMode::Blend,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: vertex_state,
+            fragment: Some(fragment_state),
+            primitive: primitive_state,
+            depth_stencil,
+            multisample: multisample_state,
+            // If the pipeline will be used with a multiview render pass, this
+            // indicates how many array layers the attachments will have.
+            multiview: None,
+            // Useful for optimizing shader compilation on Android
+            cache: None,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use crate::fullscreen;
+use crate::RenderView;
+
+/// Filter used to reduce each block of rendered texels to a single output pixel
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Filter {
+    /// Take the texel at the center of the block
+    #[default]
+    Nearest = 0,
+    /// Average of all texels in the block
+    Box = 1,
+    /// Most frequent color in the block
+    Majority = 2,
+    /// Mean of the least varying block quadrant, keeps hard edges from blurring
+    EdgePreserving = 3,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 4] = [
+        Filter::Nearest,
+        Filter::Box,
+        Filter::Majority,
+        Filter::EdgePreserving,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filter::Nearest => "Nearest",
+            Filter::Box => "Box",
+            Filter::Majority => "Majority",
+            Filter::EdgePreserving => "Edge preserving",
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct UniformData {
+    src_size: [u32; 2],
+    dst_size: [u32; 2],
+    filter: u32,
+    alpha_threshold: f32,
+    _pad: [u32; 2],
+}
+
+/// Image-space pass which downsamples a `RenderView` to pixel-art resolution.
+///
+/// Owns the low resolution output target.  The source view is bound at creation time and must
+/// outlive the pixelizer.
+pub struct Pixelizer {
+    filter: Filter,
+    alpha_threshold: f32,
+    src_size: glam::UVec2,
+    pub target: RenderView,
+    /// Per output pixel, the source texel whose color the filter result is closest to.
+    /// Companion maps sample the other render targets there so they match the color sprite.
+    pub source_texels: wgpu::TextureView,
+
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Pixelizer {
+    pub const MIN_SIZE: u32 = 16;
+    pub const MAX_SIZE: u32 = 256;
+    pub const DEFAULT_ALPHA_THRESHOLD: f32 = 0.5;
+    pub const SOURCE_TEXEL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg32Uint;
+
+    pub fn new(size: &[u32; 2], source: &RenderView, device: &wgpu::Device) -> Self {
+        let target = RenderView::new(size, device);
+        let source_texels = Self::create_source_texels(size, device);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pixelize Uniform Buffer"),
+            size: std::mem::size_of::<UniformData>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = fullscreen::bind_group_layout("Pixelize BindGroupLayout", device);
+        let bind_group = fullscreen::bind_group(
+            "Pixelize BindGroup",
+            &bind_group_layout,
+            &source.view,
+            &fullscreen::nearest_sampler(device),
+            &uniform_buffer,
+            device,
+        );
+
+        let pipeline = fullscreen::create_multi_target_pipeline(
+            "Pixelize Pipeline",
+            include_str!("../shader/pixelize.glsl"),
+            &[&bind_group_layout],
+            &[target.desc.format, Self::SOURCE_TEXEL_FORMAT],
+            device,
+        );
+
+        Self {
+            filter: Filter::default(),
+            alpha_threshold: Self::DEFAULT_ALPHA_THRESHOLD,
+            src_size: source.size(),
+            target,
+            source_texels,
+            uniform_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    pub fn alpha_threshold(&self) -> f32 {
+        self.alpha_threshold
+    }
+
+    /// Pixels whose filtered alpha is at least `threshold` become opaque, the rest fully
+    /// transparent
+    pub fn set_alpha_threshold(&mut self, threshold: f32) {
+        self.alpha_threshold = threshold.clamp(0.0, 1.0);
+    }
+
+    /// Recreate the output target with new dimensions.  Sizes are clamped to
+    /// [`Pixelizer::MIN_SIZE`, `Pixelizer::MAX_SIZE`]
+    pub fn resize(&mut self, size: &[u32; 2], device: &wgpu::Device) {
+        let size = size.map(|s| s.clamp(Self::MIN_SIZE, Self::MAX_SIZE));
+        if glam::UVec2::from(size) != self.target.size() {
+            self.target = RenderView::new(&size, device);
+            self.source_texels = Self::create_source_texels(&size, device);
+        }
+    }
+
+    fn create_source_texels(size: &[u32; 2], device: &wgpu::Device) -> wgpu::TextureView {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Pixelize Source Texels"),
+                size: wgpu::Extent3d {
+                    width: size[0],
+                    height: size[1],
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: Self::SOURCE_TEXEL_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&Default::default())
+    }
+
+    pub fn render(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let uniform_data = UniformData {
+            src_size: self.src_size.into(),
+            dst_size: self.target.size().into(),
+            filter: self.filter as u32,
+            alpha_threshold: self.alpha_threshold,
+            _pad: [0; 2],
+        };
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[uniform_data]),
+        );
+
+        fullscreen::draw_multi_target(
+            "Pixelize Pass",
+            &self.pipeline,
+            &self.bind_group,
+            &[&self.target.view, &self.source_texels],
+            device,
+            queue,
+        );
+    }
+}
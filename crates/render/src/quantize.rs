@@ -0,0 +1,584 @@
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+use crate::dither::Dither;
+use crate::fullscreen;
+use crate::pixelize::Pixelizer;
+use crate::RenderView;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PaletteEntry {
+    lab: [f32; 4],
+    rgb: [f32; 4],
+}
+
+/// Matches the scalar members of the `Palette` uniform block in quantize.glsl.  Palette entries
+/// follow at offset `size_of::<UniformHeader>()`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct UniformHeader {
+    count: u32,
+    dither: u32,
+    strength: f32,
+    spread: f32,
+    origin: [i32; 2],
+    size: [u32; 2],
+    threshold_size: u32,
+    _pad: [u32; 3],
+}
+
+/// Image-space pass mapping every pixel of the pixelated image to the closest palette color,
+/// optionally dithered.
+///
+/// Runs on the pixelizer output and is a pass-through while no palette is set, so its target is
+/// always the final pixel-art image.
+pub struct Quantizer {
+    palette: Option<palette::Palette>,
+    num_entries: u32,
+    spread: f32,
+    dither: Dither,
+    dither_strength: f32,
+    threshold_size: u32,
+    pub target: RenderView,
+
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+
+    // Ordered dithering threshold map and the palette indices written by error diffusion
+    dither_bind_group_layout: wgpu::BindGroupLayout,
+    dither_bind_group: wgpu::BindGroup,
+    /// Threshold maps by ordered dither mode, created on first use.  The blue noise map is
+    /// expensive to generate.
+    threshold_textures: HashMap<Dither, wgpu::TextureView>,
+    index_texture: wgpu::TextureView,
+    diffuser: Option<Diffuser>,
+}
+
+/// Compute state for error diffusion, see quantize.glsl
+struct Diffuser {
+    bind_group: wgpu::BindGroup,
+    /// Wavefront step numbers, one per `step_stride` bytes, selected by dynamic offset
+    step_bind_group: wgpu::BindGroup,
+    step_stride: u32,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Quantizer {
+    pub fn new(source: &RenderView, device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let target = RenderView::new(&source.size().into(), device);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Quantize Uniform Buffer"),
+            size: (std::mem::size_of::<UniformHeader>()
+                + palette::Palette::MAX_COLORS * std::mem::size_of::<PaletteEntry>())
+                as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sampler = fullscreen::nearest_sampler(device);
+        let bind_group_layout = fullscreen::bind_group_layout("Quantize BindGroupLayout", device);
+        let bind_group = fullscreen::bind_group(
+            "Quantize BindGroup",
+            &bind_group_layout,
+            &source.view,
+            &sampler,
+            &uniform_buffer,
+            device,
+        );
+
+        // Palette indices written by error diffusion, sized for the largest target
+        let diffusion = Self::supports_error_diffusion(device);
+        let index_texture = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Quantize Index Texture"),
+                size: wgpu::Extent3d {
+                    width: Pixelizer::MAX_SIZE,
+                    height: Pixelizer::MAX_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R32Uint,
+                usage: if diffusion {
+                    wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING
+                } else {
+                    wgpu::TextureUsages::TEXTURE_BINDING
+                },
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let dither_bind_group_layout = Self::dither_bind_group_layout(device);
+        let dither_bind_group = Self::create_dither_bind_group(
+            &dither_bind_group_layout,
+            &threshold_texture(1, &[0.5], device, queue),
+            &index_texture,
+            device,
+        );
+
+        let pipeline = fullscreen::create_pipeline(
+            "Quantize Pipeline",
+            include_str!("../shader/quantize.glsl"),
+            &[&bind_group_layout, &dither_bind_group_layout],
+            target.desc.format,
+            device,
+        );
+
+        let diffuser = diffusion.then(|| Diffuser::new(&bind_group_layout, &index_texture, device));
+
+        Self {
+            palette: None,
+            num_entries: 0,
+            spread: 0.0,
+            dither: Dither::None,
+            dither_strength: 1.0,
+            threshold_size: 1,
+            target,
+            uniform_buffer,
+            sampler,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+            dither_bind_group_layout,
+            dither_bind_group,
+            threshold_textures: HashMap::new(),
+            index_texture,
+            diffuser,
+        }
+    }
+
+    /// Error diffusion runs as a compute pass writing a storage texture, which WebGL2 lacks
+    pub fn supports_error_diffusion(device: &wgpu::Device) -> bool {
+        let limits = device.limits();
+        limits.max_compute_workgroups_per_dimension > 0
+            && limits.max_storage_buffers_per_shader_stage > 0
+            && limits.max_storage_textures_per_shader_stage > 0
+    }
+
+    /// Rebind to a new source view, recreating the target to match its size
+    pub fn resize(&mut self, source: &RenderView, device: &wgpu::Device) {
+        self.target = RenderView::new(&source.size().into(), device);
+        self.bind_group = fullscreen::bind_group(
+            "Quantize BindGroup",
+            &self.bind_group_layout,
+            &source.view,
+            &self.sampler,
+            &self.uniform_buffer,
+            device,
+        );
+    }
+
+    pub fn palette(&self) -> Option<&palette::Palette> {
+        self.palette.as_ref()
+    }
+
+    /// Set palette to quantize to, or `None` to pass pixels through unchanged.  Palettes are
+    /// truncated to `palette::Palette::MAX_COLORS` entries, `palette::Palette::load` rejects
+    /// larger files.
+    pub fn set_palette(&mut self, palette: Option<&palette::Palette>, queue: &wgpu::Queue) {
+        self.palette = palette.cloned();
+
+        let colors = match &self.palette {
+            Some(palette) => &palette.colors[..palette.len().min(palette::Palette::MAX_COLORS)],
+            None => &[][..],
+        };
+
+        let linear: Vec<glam::Vec3> = colors
+            .iter()
+            .map(|c| palette::oklab::srgb8_to_linear(*c))
+            .collect();
+        let entries: Vec<PaletteEntry> = colors
+            .iter()
+            .zip(&linear)
+            .map(|(c, rgb)| PaletteEntry {
+                lab: palette::oklab::from_srgb8(*c).extend(0.0).into(),
+                rgb: rgb.extend(1.0).into(),
+            })
+            .collect();
+
+        self.num_entries = entries.len() as u32;
+        self.spread = mean_neighbour_distance(&linear);
+        if !entries.is_empty() {
+            queue.write_buffer(
+                &self.uniform_buffer,
+                std::mem::size_of::<UniformHeader>() as u64,
+                bytemuck::cast_slice(&entries),
+            );
+        }
+    }
+
+    pub fn dither(&self) -> (Dither, f32) {
+        (self.dither, self.dither_strength)
+    }
+
+    /// Select dithering mode and its strength in [0,1]
+    pub fn set_dither(
+        &mut self,
+        dither: Dither,
+        strength: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.dither_strength = strength.clamp(0.0, 1.0);
+        if dither == self.dither {
+            return;
+        }
+        self.dither = dither;
+
+        if let Some(size) = dither.threshold_size() {
+            let view = self.threshold_textures.entry(dither).or_insert_with(|| {
+                let values = dither.threshold_map().unwrap();
+                threshold_texture(size, &values, device, queue)
+            });
+            self.threshold_size = size;
+            self.dither_bind_group = Self::create_dither_bind_group(
+                &self.dither_bind_group_layout,
+                view,
+                &self.index_texture,
+                device,
+            );
+        }
+    }
+
+    /// Quantize the source image.  `origin` is the output pixel ordered dither patterns are
+    /// anchored to.  Error diffusion falls back to undithered quantization on devices without
+    /// compute support.
+    pub fn render(&self, origin: glam::IVec2, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let dither = match self.dither.is_error_diffusion() && self.diffuser.is_none() {
+            true => Dither::None,
+            false => self.dither,
+        };
+
+        let header = UniformHeader {
+            count: self.num_entries,
+            dither: dither.shader_mode(),
+            strength: self.dither_strength,
+            spread: self.spread,
+            origin: origin.into(),
+            size: self.target.size().into(),
+            threshold_size: self.threshold_size,
+            _pad: [0; 3],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[header]));
+
+        if let Some(diffuser) = &self.diffuser {
+            if self.num_entries > 0 && dither.is_error_diffusion() {
+                diffuser.run(&self.bind_group, self.target.size(), device, queue);
+            }
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Quantize Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Quantize Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.dither_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    fn dither_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Dither BindGroupLayout"),
+        })
+    }
+
+    fn create_dither_bind_group(
+        layout: &wgpu::BindGroupLayout,
+        threshold_view: &wgpu::TextureView,
+        index_view: &wgpu::TextureView,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(threshold_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(index_view),
+                },
+            ],
+            label: Some("Dither BindGroup"),
+        })
+    }
+}
+
+impl Diffuser {
+    /// Rows handled per workgroup
+    const WORKGROUP_SIZE: u32 = 64;
+
+    /// `layout` is the quantize pass layout, `index_view` the texture the fragment pass reads
+    /// the chosen palette indices from
+    fn new(
+        layout: &wgpu::BindGroupLayout,
+        index_view: &wgpu::TextureView,
+        device: &wgpu::Device,
+    ) -> Self {
+        let max_pixels = (Pixelizer::MAX_SIZE * Pixelizer::MAX_SIZE) as u64;
+        let error_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Quantize Error Buffer"),
+            size: max_pixels * std::mem::size_of::<glam::Vec4>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::R32Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Diffuse BindGroupLayout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(index_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: error_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Diffuse BindGroup"),
+        });
+
+        // Every step number the largest target needs, each at a dynamic offset boundary
+        let step_stride = device
+            .limits()
+            .min_uniform_buffer_offset_alignment
+            .max(std::mem::size_of::<u32>() as u32);
+        let words_per_step = (step_stride / std::mem::size_of::<u32>() as u32) as usize;
+        let max_steps = Self::step_count([Pixelizer::MAX_SIZE; 2].into());
+        let mut steps = vec![0u32; max_steps as usize * words_per_step];
+        for step in 0..max_steps {
+            steps[step as usize * words_per_step] = step;
+        }
+        let step_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Diffuse Step Buffer"),
+            contents: bytemuck::cast_slice(&steps),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let step_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("Diffuse Step BindGroupLayout"),
+            });
+        let step_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &step_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &step_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<u32>() as u64),
+                }),
+            }],
+            label: Some("Diffuse Step BindGroup"),
+        });
+
+        let mut defines = wgpu::naga::FastHashMap::default();
+        defines.insert("ERROR_DIFFUSION".to_string(), "1".to_string());
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("DiffuseShader"),
+            source: wgpu::ShaderSource::Glsl {
+                shader: include_str!("../shader/quantize.glsl").into(),
+                stage: wgpu::naga::ShaderStage::Compute,
+                defines,
+            },
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Diffuse Pipeline Layout"),
+            bind_group_layouts: &[layout, &bind_group_layout, &step_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Diffuse Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Self {
+            bind_group,
+            step_bind_group,
+            step_stride,
+            pipeline,
+        }
+    }
+
+    /// Wavefront steps covering a `size` image, rows start two steps after the row above
+    fn step_count(size: glam::UVec2) -> u32 {
+        size.x + 2 * size.y.saturating_sub(1)
+    }
+
+    /// Diffuse the quantization error over a `size` image read through `bind_group`, the
+    /// quantize pass bind group
+    fn run(
+        &self,
+        bind_group: &wgpu::BindGroup,
+        size: glam::UVec2,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Diffuse Encoder"),
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Diffuse Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.bind_group, &[]);
+            // Dispatches are ordered, each step sees the errors written by the ones before
+            for step in 0..Self::step_count(size) {
+                compute_pass.set_bind_group(2, &self.step_bind_group, &[step * self.step_stride]);
+                compute_pass.dispatch_workgroups(size.y.div_ceil(Self::WORKGROUP_SIZE), 1, 1);
+            }
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// Single channel `size` x `size` threshold map for ordered dithering
+fn threshold_texture(
+    size: u32,
+    values: &[f32],
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> wgpu::TextureView {
+    let extent = wgpu::Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Dither Threshold Texture"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::R32Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            aspect: wgpu::TextureAspect::All,
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        bytemuck::cast_slice(values),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size),
+            rows_per_image: Some(size),
+        },
+        extent,
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Average distance from each color to its closest neighbour.  Used as the ordered dither
+/// amplitude so the pattern is just strong enough to toggle between adjacent palette entries.
+fn mean_neighbour_distance(colors: &[glam::Vec3]) -> f32 {
+    if colors.len() < 2 {
+        return 0.0;
+    }
+    let total: f32 = colors
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            colors
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, b)| a.distance(*b))
+                .fold(f32::MAX, f32::min)
+        })
+        .sum();
+    total / colors.len() as f32
+}
@@ -1,7 +1,10 @@
+use std::collections::HashMap;
+
 use wgpu::util::DeviceExt;
 
 use crate::camera;
 use crate::light;
+use crate::texture;
 
 /// 3D scene for simple wgpu renderer
 ///
@@ -10,51 +13,244 @@ pub struct Scene {
     pub bbox: model::BoundingBox,
     pub camera: WGPUCamera,
     pub light: WGPULight,
-    pub _materials: Vec<WGPUMaterial>,
+    /// Uploaded model textures, keyed by `model::Model::textures` index and whether they
+    /// were uploaded as sRGB
+    pub textures: HashMap<(usize, bool), texture::Texture>,
+    /// Indexed like `model::Model::materials`, 0 is the default material
+    pub materials: Vec<WGPUMaterial>,
     pub skins: Vec<WGPUSkin>,
     pub meshes: Vec<WGPUMesh>,
+    pub layouts: Layouts,
+    /// Model transform of every mesh, each in its own slot at `WGPUMesh::transform_offset`
+    pub transform_buffer: wgpu::Buffer,
+    /// Normal transform (inv-transpose of model-view) of every mesh, slots as above
+    pub normal_transform_buffer: wgpu::Buffer,
+
+    /// Model node hierarchy and clips, for posing
+    pub nodes: Vec<model::Node>,
+    pub animations: Vec<model::Animation>,
+    /// Node whose motion is the model's root motion, see `root_node`
+    pub root_node: Option<usize>,
+    /// Model space position of `root_node` in the current pose
+    pub root_position: glam::Vec3,
 }
 
 impl Scene {
-    pub fn from_model(model: &model::Model, device: &wgpu::Device) -> Self {
+    /// Material `mesh` is drawn with
+    pub fn material(&self, mesh: &WGPUMesh) -> &WGPUMaterial {
+        &self.materials[mesh.material_id.unwrap_or(0) as usize]
+    }
+
+    pub fn from_model(model: &model::Model, device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let primitive_count: usize = model.meshes.iter().map(|m| m.primitives.len()).sum();
+        let transform_stride = transform_stride(device);
+        let create_transform_buffer = |label| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: transform_stride * primitive_count.max(1) as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        let layouts = Layouts::new(device);
         let mut scene = Self {
             bbox: model.bbox,
-            camera: WGPUCamera::with_looking_at(&model.bbox, device),
-            light: WGPULight::new(device),
-            _materials: Vec::new(),
+            camera: WGPUCamera::with_looking_at(&model.bbox, &layouts.camera, device),
+            light: WGPULight::new(&layouts.light, device, queue),
+            textures: HashMap::new(),
+            materials: Vec::new(),
             skins: Vec::new(),
             meshes: Vec::new(),
+            layouts,
+            transform_buffer: create_transform_buffer("Model Transform Buffer"),
+            normal_transform_buffer: create_transform_buffer("Normal Transform Buffer"),
+            nodes: model.nodes.clone(),
+            animations: model.animations.clone(),
+            root_node: root_node(model),
+            root_position: glam::Vec3::ZERO,
         };
 
+        for material in &model.materials {
+            for (idx, srgb) in WGPUMaterial::texture_slots(material) {
+                let Some(idx) = idx else {
+                    continue;
+                };
+                scene.textures.entry((idx, srgb)).or_insert_with(|| {
+                    let label = format!("Model Texture {}", idx);
+                    texture::Texture::from_model_texture(
+                        device,
+                        queue,
+                        &model.textures[idx],
+                        srgb,
+                        Some(&label),
+                    )
+                });
+            }
+        }
+
+        // Stand in for the textures a material doesn't have
+        let fallbacks = WGPUMaterial::fallback_textures(device, queue);
+        for material in &model.materials {
+            let slots = WGPUMaterial::texture_slots(material);
+            let textures = std::array::from_fn(|i| match slots[i] {
+                (Some(idx), srgb) => &scene.textures[&(idx, srgb)],
+                (None, _) => &fallbacks[i],
+            });
+            scene.materials.push(WGPUMaterial::new(
+                material,
+                &textures,
+                &scene.layouts.material,
+                device,
+            ));
+        }
+
+        let rest_globals = model.rest_pose().global_transforms(&model.nodes);
+        if let Some(root) = scene.root_node {
+            scene.root_position = rest_globals[root].w_axis.truncate();
+        }
+        for skin in &model.skins {
+            scene
+                .skins
+                .push(WGPUSkin::new(skin, &rest_globals, device, queue));
+        }
+
         for mesh in &model.meshes {
-            let skin_id = if let Some(skin) = &mesh.skin {
-                scene.skins.push(WGPUSkin::new(skin, device));
-                Some((scene.skins.len() - 1) as u32)
-            } else {
-                None
+            // Joint matrices already place skinned vertices in model space
+            let skin = mesh.skin.map(|skin| &scene.skins[skin]);
+            let transform = match skin {
+                Some(_) => glam::Mat4::IDENTITY,
+                None => mesh.transform,
             };
 
             for primitive in &mesh.primitives {
-                let mut wgpu_mesh =
-                    WGPUMesh::from_model_primitive(primitive, mesh.transform, device);
-                wgpu_mesh.skin_id = skin_id;
-                wgpu_mesh.material_id = None;
+                // Ids start at 1, 0 marks the background in the edge detection buffers
+                let object_id = scene.meshes.len() as u32 + 1;
+                let transform_offset = scene.meshes.len() as u64 * transform_stride;
+                let mut wgpu_mesh = WGPUMesh::from_model_primitive(
+                    primitive,
+                    object_id,
+                    [&scene.transform_buffer, &scene.normal_transform_buffer],
+                    transform_offset as u32,
+                    skin.map(|s| &s.joint_buffer),
+                    &scene.layouts.mesh,
+                    device,
+                );
+                wgpu_mesh.transform = transform;
+                wgpu_mesh.node = mesh.node;
+                wgpu_mesh.skin_id = mesh.skin.map(|skin| skin as u32);
+                wgpu_mesh.material_id = Some(primitive.material_idx);
+                wgpu_mesh.mirrored = transform.determinant() < 0.0;
                 scene.meshes.push(wgpu_mesh);
             }
         }
         scene
     }
+
+    /// Move meshes and skin joints to `pose`.  Unskinned meshes follow their node, skinned
+    /// meshes get new joint matrices.  Returns whether the winding of any mesh flipped, which
+    /// requires new pipelines.
+    pub fn set_pose(&mut self, pose: &model::Pose, queue: &wgpu::Queue) -> bool {
+        let globals = pose.global_transforms(&self.nodes);
+        if let Some(root) = self.root_node {
+            self.root_position = globals[root].w_axis.truncate();
+        }
+        let mut winding_changed = false;
+        for mesh in &mut self.meshes {
+            if mesh.skin_id.is_none() {
+                mesh.transform = globals[mesh.node];
+                let mirrored = mesh.transform.determinant() < 0.0;
+                winding_changed |= mirrored != mesh.mirrored;
+                mesh.mirrored = mirrored;
+            }
+            mesh.set_morph_weights(&pose.weights[mesh.node], queue);
+        }
+        for skin in &self.skins {
+            skin.update(&globals, queue);
+        }
+        winding_changed
+    }
+
+    /// Upload the transforms of all meshes in one write.  `placement` moves the whole model,
+    /// normal transforms are for `view_matrix`.
+    pub fn write_transforms(
+        &self,
+        placement: glam::Mat4,
+        view_matrix: glam::Mat4,
+        queue: &wgpu::Queue,
+    ) {
+        let size = self.transform_buffer.size() as usize;
+        let (mut transforms, mut normal_transforms) = (vec![0u8; size], vec![0u8; size]);
+        for mesh in &self.meshes {
+            let model_matrix = placement * mesh.transform;
+            let normal_transform = crate::generate_normal_transform(view_matrix, model_matrix);
+            let slot = mesh.transform_offset as usize..mesh.transform_offset as usize + 64;
+            transforms[slot.clone()].copy_from_slice(bytemuck::bytes_of(&model_matrix));
+            normal_transforms[slot].copy_from_slice(bytemuck::bytes_of(&normal_transform));
+        }
+        queue.write_buffer(&self.transform_buffer, 0, &transforms);
+        queue.write_buffer(&self.normal_transform_buffer, 0, &normal_transforms);
+    }
+}
+
+/// Bytes between the mesh slots of `Scene::transform_buffer`, padded to the device's dynamic
+/// offset alignment
+fn transform_stride(device: &wgpu::Device) -> u64 {
+    let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+    (std::mem::size_of::<glam::Mat4>() as u64).div_ceil(alignment) * alignment
+}
+
+/// Root of the first skin's joint hierarchy, or the first top-level node for unskinned models
+fn root_node(model: &model::Model) -> Option<usize> {
+    match model.skins.first() {
+        Some(skin) => skin.skeleton.or_else(|| {
+            skin.joints
+                .iter()
+                .copied()
+                .find(|&joint| match model.nodes[joint].parent {
+                    Some(parent) => !skin.joints.contains(&parent),
+                    None => true,
+                })
+        }),
+        None => model.nodes.iter().position(|node| node.parent.is_none()),
+    }
+}
+
+/// Bind group layouts of the mesh render pass, created once and shared by all bind groups and
+/// pipelines using them
+pub struct Layouts {
+    pub camera: wgpu::BindGroupLayout,
+    pub mesh: wgpu::BindGroupLayout,
+    pub light: wgpu::BindGroupLayout,
+    pub material: wgpu::BindGroupLayout,
+}
+
+impl Layouts {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            camera: WGPUCamera::bind_group_layout(device),
+            mesh: WGPUMesh::bind_group_layout(device),
+            light: WGPULight::bind_group_layout(device),
+            material: WGPUMaterial::bind_group_layout(device),
+        }
+    }
 }
 
 /// Camera controller and WGPU state for device-side camera data
 pub struct WGPUCamera {
     pub controller: camera::Camera,
     pub view_proj_buffer: wgpu::Buffer,
+    /// View matrix alone, for linear view depth in the edge detection buffers
+    pub view_buffer: wgpu::Buffer,
     pub bind_group: wgpu::BindGroup,
 }
 
 impl WGPUCamera {
-    pub fn with_looking_at(bbox: &model::BoundingBox, device: &wgpu::Device) -> Self {
+    pub fn with_looking_at(
+        bbox: &model::BoundingBox,
+        layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> Self {
         // Generate initial viewing params, camera is looking in -Z direction at
         // center of model's bounding box
         let bbox_mid = bbox.mid();
@@ -79,35 +275,60 @@ impl WGPUCamera {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
+        let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera View Buffer"),
+            size: std::mem::size_of::<glam::Mat4>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &WGPUCamera::bind_group_layout(device),
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: view_proj_buffer.as_entire_binding(),
-            }],
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: view_proj_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: view_buffer.as_entire_binding(),
+                },
+            ],
             label: Some("Camera BindGroup"),
         });
 
         Self {
             controller,
             view_proj_buffer,
+            view_buffer,
             bind_group,
         }
     }
 
-    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
             label: Some("Camera BindGroupLayout"),
         })
     }
@@ -117,11 +338,17 @@ impl WGPUCamera {
 pub struct WGPULight {
     pub controller: light::Light,
     pub buffer: wgpu::Buffer,
+    pub cel_buffer: wgpu::Buffer,
+    /// Band colors for `ShadingModel::CelRamp`, sampled left (darkest) to right (brightest)
+    pub ramp: texture::Texture,
     pub bind_group: wgpu::BindGroup,
 }
 
 impl WGPULight {
-    pub fn new(device: &wgpu::Device) -> Self {
+    /// Width of the default grey ramp
+    const DEFAULT_RAMP_WIDTH: u32 = 256;
+
+    pub fn new(layout: &wgpu::BindGroupLayout, device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
         let controller = light::Light::default();
 
         // Buffer to store light params as uniform data
@@ -137,18 +364,32 @@ impl WGPULight {
             std::mem::size_of::<light::UniformData>()
         );
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &WGPULight::bind_group_layout(device),
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
-            label: Some("Light BindGroup"),
+        let cel_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cel Buffer"),
+            size: std::mem::size_of::<light::CelUniformData>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
 
+        // Black to white gradient until a ramp is set
+        let default_ramp = image::RgbaImage::from_fn(Self::DEFAULT_RAMP_WIDTH, 1, |x, _| {
+            let v = (x * 255 / (Self::DEFAULT_RAMP_WIDTH - 1)) as u8;
+            image::Rgba([v, v, v, 255])
+        });
+        let ramp = texture::Texture::from_image(
+            device,
+            queue,
+            &default_ramp.into(),
+            Some("Cel Ramp Texture"),
+        );
+
+        let bind_group = Self::create_bind_group(&buffer, &cel_buffer, &ramp, layout, device);
+
         Self {
             controller,
             buffer,
+            cel_buffer,
+            ramp,
             bind_group,
         }
     }
@@ -159,20 +400,103 @@ impl WGPULight {
             0,
             bytemuck::cast_slice(&[self.controller.uniform_data]),
         );
+        queue.write_buffer(
+            &self.cel_buffer,
+            0,
+            bytemuck::cast_slice(&[self.controller.cel.uniform_data()]),
+        );
+    }
+
+    /// Replace the cel shading ramp.  The image is treated as sRGB and sampled along its middle
+    /// row.
+    pub fn set_ramp(
+        &mut self,
+        ramp: &image::RgbaImage,
+        layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.ramp = texture::Texture::from_image(
+            device,
+            queue,
+            &ramp.clone().into(),
+            Some("Cel Ramp Texture"),
+        );
+        self.bind_group =
+            Self::create_bind_group(&self.buffer, &self.cel_buffer, &self.ramp, layout, device);
+    }
+
+    fn create_bind_group(
+        buffer: &wgpu::Buffer,
+        cel_buffer: &wgpu::Buffer,
+        ramp: &texture::Texture,
+        layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: cel_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&ramp.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&ramp.sampler),
+                },
+            ],
+            label: Some("Light BindGroup"),
+        })
     }
 
-    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
             label: Some("Light BindGroupLayout"),
         })
     }
@@ -180,27 +504,53 @@ impl WGPULight {
 
 pub struct WGPUMesh {
     pub transform: glam::Mat4,
+    /// Index of the model node the mesh is attached to
+    pub node: usize,
     pub skin_id: Option<u32>,
     pub material_id: Option<u32>,
+    /// Bounding box center in mesh space, for depth sorting
+    pub center: glam::Vec3,
+    /// `transform` flips handedness, so triangles wind clockwise.  Selects the pipeline and
+    /// hull variant at draw time.
+    pub mirrored: bool,
 
     pub num_triangles: u32,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: Option<wgpu::Buffer>,
     pub normal_buffer: Option<wgpu::Buffer>,
     pub texcoord_buffer: Option<wgpu::Buffer>,
+    pub joints_buffer: Option<wgpu::Buffer>,
+    pub weights_buffer: Option<wgpu::Buffer>,
+    pub tangent_buffer: Option<wgpu::Buffer>,
+    pub color_buffer: Option<wgpu::Buffer>,
+    /// Target count, vertex count and current weights of the morph targets
+    pub morph_weights_buffer: Option<wgpu::Buffer>,
+    pub morph_target_count: usize,
 
-    //pub transform_buffer: wgpu::Buffer,
-    pub normal_transform_buffer: wgpu::Buffer,
+    /// Byte offset of the mesh's slot in the scene's transform buffers, the dynamic offset of
+    /// both transform bindings
+    pub transform_offset: u32,
     pub bind_group: wgpu::BindGroup,
 }
 
 impl WGPUMesh {
+    /// `transform_buffers` are the scene's shared model and normal transform buffers, the
+    /// mesh's transforms live at `transform_offset` in them
     pub fn from_model_primitive(
         prim: &model::Primitive,
-        transform: glam::Mat4,
+        object_id: u32,
+        transform_buffers: [&wgpu::Buffer; 2],
+        transform_offset: u32,
+        joint_buffer: Option<&wgpu::Buffer>,
+        layout: &wgpu::BindGroupLayout,
         device: &wgpu::Device,
     ) -> Self {
         let mut num_triangles = prim.positions.len() as u32;
+        let (min, max) = prim.positions.iter().fold(
+            (glam::Vec3::splat(f32::MAX), glam::Vec3::splat(f32::MIN)),
+            |(min, max), &p| (min.min(p), max.max(p)),
+        );
+        let center = (min + max) * 0.5;
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -243,53 +593,178 @@ impl WGPUMesh {
             None
         };
 
-        // Buffer to store the model tranform
-        let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Model Transform Buffer"),
-            contents: bytemuck::cast_slice(&[transform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        // Only the first joint/weight set is used for skinning
+        let (joints_buffer, weights_buffer) = match (prim.joints.first(), prim.weights.first()) {
+            (Some(joints), Some(weights)) if joint_buffer.is_some() && !joints.is_empty() => {
+                let joints = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Joints Buffer"),
+                    contents: bytemuck::cast_slice(joints.as_slice()),
+                    usage: wgpu::BufferUsages::VERTEX,
+                });
+                let weights = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Weights Buffer"),
+                    contents: bytemuck::cast_slice(weights.as_slice()),
+                    usage: wgpu::BufferUsages::VERTEX,
+                });
+                (Some(joints), Some(weights))
+            }
+            _ => (None, None),
+        };
 
-        // Buffer to store the normal transform (inv-transpose of model-view)
-        let normal_transform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Normal Transform Buffer"),
-            size: std::mem::size_of::<glam::Mat4>() as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        // Tangents are only meaningful alongside normals
+        let tangent_buffer = if !prim.tangents.is_empty() && !prim.normals.is_empty() {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Tangent Buffer"),
+                contents: bytemuck::cast_slice(prim.tangents.as_slice()),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            Some(buffer)
+        } else {
+            None
+        };
+
+        let color_buffer = if !prim.colors.is_empty() {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Color Buffer"),
+                contents: bytemuck::cast_slice(prim.colors.as_slice()),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            Some(buffer)
+        } else {
+            None
+        };
+
+        // Unskinned meshes still need something bound to the joint matrix slot
+        let identity_joints;
+        let joint_buffer = match joint_buffer {
+            Some(buffer) => buffer,
+            None => {
+                identity_joints = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Identity Joint Buffer"),
+                    contents: bytemuck::cast_slice(&[glam::Mat4::IDENTITY]),
+                    usage: wgpu::BufferUsages::STORAGE,
+                });
+                &identity_joints
+            }
+        };
+
+        let morph_target_count = prim.morph_targets.len();
+        let (morph_deltas_buffer, morph_weights_buffer) = if morph_target_count > 0 {
+            // Position, normal and tangent deltas, [target][attribute][vertex]
+            let deltas: Vec<glam::Vec4> = prim
+                .morph_targets
+                .iter()
+                .flat_map(|target| {
+                    target
+                        .positions
+                        .iter()
+                        .chain(&target.normals)
+                        .chain(&target.tangents)
+                })
+                .map(|d| d.extend(0.0))
+                .collect();
+            let deltas = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Morph Deltas Buffer"),
+                contents: bytemuck::cast_slice(&deltas),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+            let weights = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Morph Weights Buffer"),
+                contents: bytemuck::cast_slice(&morph_weights_data(
+                    &prim.morph_weights,
+                    morph_target_count,
+                    prim.positions.len(),
+                )),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            });
+            (deltas, Some(weights))
+        } else {
+            let empty = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Empty Morph Buffer"),
+                contents: bytemuck::cast_slice(&[0u32; 4]),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+            (empty, None)
+        };
+
+        // The transforms are bound at the start of the shared buffers, the dynamic offset
+        // selects this mesh's slot
+        let transform_binding = |buffer| {
+            wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<glam::Mat4>() as u64),
+            })
+        };
+
+        // Object id for outline detection and the material id (offset by one, 0 is background)
+        // for id maps, padded to 16 bytes
+        let material_id = prim.material_idx + 1;
+        let object_id_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Object Id Buffer"),
+            contents: bytemuck::cast_slice(&[object_id, material_id, 0, 0]),
+            usage: wgpu::BufferUsages::UNIFORM,
         });
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &WGPUMesh::bind_group_layout(device),
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: transform_buffer.as_entire_binding(),
+                    resource: transform_binding(transform_buffers[0]),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: normal_transform_buffer.as_entire_binding(),
+                    resource: transform_binding(transform_buffers[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: object_id_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: joint_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: morph_deltas_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: morph_weights_buffer
+                        .as_ref()
+                        .unwrap_or(&morph_deltas_buffer)
+                        .as_entire_binding(),
                 },
             ],
-            label: Some("Camera BindGroup"),
+            label: Some("Mesh BindGroup"),
         });
 
         Self {
-            transform,
+            transform: glam::Mat4::IDENTITY,
+            node: 0,
             skin_id: None,
             material_id: None,
+            center,
+            mirrored: false,
             num_triangles,
             vertex_buffer,
             index_buffer,
             normal_buffer,
             texcoord_buffer,
+            joints_buffer,
+            weights_buffer,
+            tangent_buffer,
+            color_buffer,
+            morph_weights_buffer,
+            morph_target_count,
 
-            //transform_buffer,
-            normal_transform_buffer,
+            transform_offset,
             bind_group,
         }
     }
 
-    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -297,7 +772,7 @@ impl WGPUMesh {
                     visibility: wgpu::ShaderStages::VERTEX,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
+                        has_dynamic_offset: true,
                         min_binding_size: None,
                     },
                     count: None,
@@ -307,6 +782,46 @@ impl WGPUMesh {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
                         min_binding_size: None,
                     },
@@ -316,14 +831,245 @@ impl WGPUMesh {
             label: Some("mesh_bind_group_layout"),
         })
     }
+
+    /// Whether the mesh is drawn with linear blend skinning
+    pub fn has_joints(&self) -> bool {
+        self.joints_buffer.is_some()
+    }
+
+    pub fn has_morph_targets(&self) -> bool {
+        self.morph_weights_buffer.is_some()
+    }
+
+    /// Upload morph target weights.  Missing weights are zero, extra ones are ignored.
+    pub fn set_morph_weights(&self, weights: &[f32], queue: &wgpu::Queue) {
+        if let Some(buffer) = &self.morph_weights_buffer {
+            // The counts in the header don't change, only write the weights after them
+            let data = morph_weights_data(weights, self.morph_target_count, 0);
+            queue.write_buffer(buffer, 8, bytemuck::cast_slice(&data[2..]));
+        }
+    }
+
+    /// Bind the mesh's bind group, with its slot of the shared transform buffer
+    pub fn set_bind_group(&self, index: u32, render_pass: &mut wgpu::RenderPass<'_>) {
+        let offset = self.transform_offset;
+        render_pass.set_bind_group(index, &self.bind_group, &[offset, offset]);
+    }
+
+    /// Bind the vertex attribute buffers in slot order: position, then normals, texcoords,
+    /// joints/weights, tangents and colors when present.  Must match the layouts built in
+    /// `pipeline::Cache`.
+    pub fn set_vertex_buffers(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        let buffers = [
+            Some(&self.vertex_buffer),
+            self.normal_buffer.as_ref(),
+            self.texcoord_buffer.as_ref(),
+            self.joints_buffer.as_ref(),
+            self.weights_buffer.as_ref(),
+            self.tangent_buffer.as_ref(),
+            self.color_buffer.as_ref(),
+        ];
+        for (slot, buffer) in buffers.into_iter().flatten().enumerate() {
+            render_pass.set_vertex_buffer(slot as u32, buffer.slice(..));
+        }
+    }
+}
+
+/// Morph weights buffer contents: target count, vertex count, then one weight per target
+fn morph_weights_data(weights: &[f32], target_count: usize, vertex_count: usize) -> Vec<u32> {
+    let mut data = vec![target_count as u32, vertex_count as u32];
+    data.extend((0..target_count).map(|t| weights.get(t).copied().unwrap_or(0.0).to_bits()));
+    data
 }
 
-pub struct WGPUSkin {}
+/// Device-side joint matrices of a skin
+pub struct WGPUSkin {
+    pub skin: model::Skin,
+    /// One skinning matrix per joint, see `model::Skin::joint_matrices`
+    pub joint_buffer: wgpu::Buffer,
+}
 
 impl WGPUSkin {
-    pub fn new(_model_skin: &model::Skin, _device: &wgpu::Device) -> Self {
-        Self {}
+    pub fn new(
+        model_skin: &model::Skin,
+        globals: &[glam::Mat4],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        let joint_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Joint Matrix Buffer"),
+            size: (model_skin.joints.len().max(1) * std::mem::size_of::<glam::Mat4>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let skin = Self {
+            skin: model_skin.clone(),
+            joint_buffer,
+        };
+        skin.update(globals, queue);
+        skin
     }
+
+    /// Upload joint matrices for the node transforms `globals`
+    pub fn update(&self, globals: &[glam::Mat4], queue: &wgpu::Queue) {
+        let matrices = self.skin.joint_matrices(globals);
+        if !matrices.is_empty() {
+            queue.write_buffer(&self.joint_buffer, 0, bytemuck::cast_slice(&matrices));
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaterialUniformData {
+    base_color: [f32; 4],
+    emissive: [f32; 3],
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+}
+
+/// Material factors and textures, bound at set 3 of the main render pipelines.  Textures are
+/// bound in the order of `texture_slots`, each followed by its sampler.
+pub struct WGPUMaterial {
+    pub alpha_mode: model::AlphaMode,
+    pub double_sided: bool,
+    #[allow(unused)]
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
 }
 
-pub struct WGPUMaterial {}
+impl WGPUMaterial {
+    pub const TEXTURE_COUNT: usize = 5;
+
+    /// Base color, metallic-roughness, normal, occlusion and emissive texture indices, with
+    /// whether the texture holds sRGB color
+    pub fn texture_slots(
+        material: &model::Material,
+    ) -> [(Option<usize>, bool); Self::TEXTURE_COUNT] {
+        [
+            (material.base_color_tex, true),
+            (material.metallic_roughness_tex, false),
+            (material.normal_tex, false),
+            (material.occlusion_tex, false),
+            (material.emissive_tex, true),
+        ]
+    }
+
+    /// Textures leaving the material factors unchanged, in `texture_slots` order
+    pub fn fallback_textures(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> [texture::Texture; Self::TEXTURE_COUNT] {
+        let sampler = wgpu::SamplerDescriptor::default();
+        let pixel = |rgba: [u8; 4], srgb: bool| {
+            let format = if srgb {
+                wgpu::TextureFormat::Rgba8UnormSrgb
+            } else {
+                wgpu::TextureFormat::Rgba8Unorm
+            };
+            texture::Texture::from_image_with_sampler(
+                device,
+                queue,
+                &image::RgbaImage::from_pixel(1, 1, image::Rgba(rgba)).into(),
+                Some("Fallback Material Texture"),
+                format,
+                &sampler,
+            )
+        };
+        [
+            pixel([255; 4], true),
+            pixel([255; 4], false),
+            // Unperturbed tangent space normal
+            pixel([128, 128, 255, 255], false),
+            pixel([255; 4], false),
+            pixel([255; 4], true),
+        ]
+    }
+
+    pub fn new(
+        material: &model::Material,
+        textures: &[&texture::Texture; Self::TEXTURE_COUNT],
+        layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> Self {
+        let uniform_data = MaterialUniformData {
+            base_color: material.base_color.to_array(),
+            emissive: material.emissive.to_array(),
+            metallic: material.metallic,
+            roughness: material.roughness,
+            normal_scale: material.normal_scale,
+            occlusion_strength: material.occlusion_strength,
+            alpha_cutoff: material.alpha_cutoff,
+        };
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Buffer"),
+            contents: bytemuck::cast_slice(&[uniform_data]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }];
+        for (i, texture) in textures.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1 + 2 * i as u32,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 + 2 * i as u32,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            });
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some("Material BindGroup"),
+        });
+
+        Self {
+            alpha_mode: material.alpha_mode,
+            double_sided: material.double_sided,
+            buffer,
+            bind_group,
+        }
+    }
+
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let mut entries = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }];
+        for i in 0..Self::TEXTURE_COUNT as u32 {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 1 + 2 * i,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 + 2 * i,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            });
+        }
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("Material BindGroupLayout"),
+        })
+    }
+}
@@ -1,3 +1,40 @@
+/// Lighting model used by the fragment shader
+#[derive(Default, Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ShadingModel {
+    /// Lambert diffuse plus ambient
+    #[default]
+    Lambert,
+    /// Diffuse quantized into flat bands with a hard specular highlight, see `light::CelShading`
+    Cel,
+    /// As `Cel`, with band colors looked up from a ramp texture
+    CelRamp,
+    /// Metallic-roughness physically based shading using all material textures
+    Pbr,
+}
+
+impl ShadingModel {
+    pub const ALL: [ShadingModel; 4] = [
+        ShadingModel::Lambert,
+        ShadingModel::Cel,
+        ShadingModel::CelRamp,
+        ShadingModel::Pbr,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShadingModel::Lambert => "Lambert",
+            ShadingModel::Cel => "Cel",
+            ShadingModel::CelRamp => "Cel (ramp)",
+            ShadingModel::Pbr => "PBR",
+        }
+    }
+
+    /// Whether the model uses the `light::CelShading` parameters
+    pub fn is_cel(&self) -> bool {
+        matches!(self, ShadingModel::Cel | ShadingModel::CelRamp)
+    }
+}
+
 /// For now assuming only a single set of a given attribute semantic per mesh (eg, single color
 /// set).
 #[derive(Default, PartialEq, Eq, Hash, Copy, Clone)]
@@ -7,6 +44,9 @@ pub struct Specification {
     pub has_texcoords: bool,
     pub has_colors: bool,
     pub has_joints: bool,
+    pub has_morph_targets: bool,
+    pub shading: ShadingModel,
+    pub alpha_mode: model::AlphaMode,
 }
 
 impl Specification {
@@ -28,6 +68,31 @@ impl Specification {
         if self.has_joints {
             defines.insert("HAS_JOINTS".to_string(), "1".to_string());
         }
+        if self.has_morph_targets {
+            defines.insert("HAS_MORPH_TARGETS".to_string(), "1".to_string());
+        }
+        match self.alpha_mode {
+            model::AlphaMode::Opaque => {}
+            model::AlphaMode::Mask => {
+                defines.insert("ALPHA_MASK".to_string(), "1".to_string());
+            }
+            model::AlphaMode::Blend => {
+                defines.insert("ALPHA_BLEND".to_string(), "1".to_string());
+            }
+        }
+        match self.shading {
+            ShadingModel::Lambert => {}
+            ShadingModel::Cel => {
+                defines.insert("SHADING_CEL".to_string(), "1".to_string());
+            }
+            ShadingModel::CelRamp => {
+                defines.insert("SHADING_CEL".to_string(), "1".to_string());
+                defines.insert("USE_RAMP".to_string(), "1".to_string());
+            }
+            ShadingModel::Pbr => {
+                defines.insert("SHADING_PBR".to_string(), "1".to_string());
+            }
+        }
 
         defines
     }
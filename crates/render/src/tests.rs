@@ -0,0 +1,236 @@
+use crate::{headless, Dither, LineColor, Outline, OutlineEdges, OutlineTechnique, Renderer};
+use glam::{UVec3, Vec3, Vec4};
+
+const ORDERED: [Dither; 4] = [
+    Dither::Bayer2,
+    Dither::Bayer4,
+    Dither::Bayer8,
+    Dither::BlueNoise,
+];
+
+/// Threshold map of `dither` converted back to ranks
+fn ranks(dither: Dither) -> Vec<usize> {
+    let size = dither.threshold_size().unwrap() as usize;
+    let values = dither.threshold_map().unwrap();
+    assert_eq!(values.len(), size * size, "{:?}", dither);
+    values
+        .iter()
+        .map(|&v| {
+            assert!(
+                v > 0.0 && v < 1.0,
+                "{:?} threshold {} out of range",
+                dither,
+                v
+            );
+            let rank = v * (size * size) as f32 - 0.5;
+            assert!((rank - rank.round()).abs() < 1e-3, "{:?}", dither);
+            rank.round() as usize
+        })
+        .collect()
+}
+
+#[test]
+fn threshold_maps_are_permutations() {
+    for dither in ORDERED {
+        let mut ranks = ranks(dither);
+        ranks.sort();
+        let expected: Vec<usize> = (0..ranks.len()).collect();
+        assert_eq!(ranks, expected, "{:?}", dither);
+    }
+}
+
+#[test]
+fn bayer_matrices() {
+    assert_eq!(ranks(Dither::Bayer2), [0, 2, 3, 1]);
+    assert_eq!(
+        ranks(Dither::Bayer4),
+        [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5]
+    );
+}
+
+#[test]
+fn blue_noise_is_deterministic() {
+    assert_eq!(
+        Dither::BlueNoise.threshold_map(),
+        Dither::BlueNoise.threshold_map()
+    );
+}
+
+#[test]
+fn unordered_modes_have_no_threshold_map() {
+    for dither in [Dither::None, Dither::FloydSteinberg, Dither::Atkinson] {
+        assert!(dither.threshold_size().is_none());
+        assert!(dither.threshold_map().is_none());
+    }
+}
+
+/// Cube with smooth normals, so the inverted hull grows outwards in every direction
+fn cube(center: Vec3, half_size: f32, material_idx: u32, node: usize) -> model::Mesh {
+    let corners: Vec<Vec3> = (0..8)
+        .map(|i| Vec3::new((i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2 & 1) as f32) * 2.0 - 1.0)
+        .collect();
+    let faces = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ];
+    let indices = faces
+        .iter()
+        .flat_map(|&[a, b, c, d]| [UVec3::new(a, b, c), UVec3::new(a, c, d)])
+        .collect();
+    let bbox = model::BoundingBox::new(
+        center - Vec3::splat(half_size),
+        center + Vec3::splat(half_size),
+    );
+    model::Mesh {
+        transform: glam::Mat4::IDENTITY,
+        bbox,
+        primitives: vec![model::Primitive {
+            material_idx,
+            indices,
+            positions: corners.iter().map(|&c| center + c * half_size).collect(),
+            normals: corners.iter().map(|c| c.normalize()).collect(),
+            ..Default::default()
+        }],
+        node,
+        skin: None,
+    }
+}
+
+#[test]
+fn outer_hull_keeps_earlier_meshes() {
+    let Ok((device, queue)) = headless::request_device(true) else {
+        return;
+    };
+    let solid = |r, g, b| model::Material {
+        base_color: Vec4::new(r, g, b, 1.0),
+        metallic: 0.0,
+        ..Default::default()
+    };
+    // A small green cube in front of the center of a large red one, framed with room for the
+    // outline.  The green cube is drawn first, so the red cube's hull covers it unless all
+    // hulls go before the meshes.
+    let front = cube(Vec3::new(0.0, 0.0, 0.8), 0.15, 1, 0);
+    let back = cube(Vec3::ZERO, 0.5, 2, 1);
+    let bbox = model::BoundingBox::new(Vec3::splat(-1.0), Vec3::splat(1.0));
+    let input = model::Model {
+        bbox,
+        nodes: vec![model::Node::default(), model::Node::default()],
+        meshes: vec![front, back],
+        materials: vec![
+            model::Material::default(),
+            solid(0.0, 1.0, 0.0),
+            solid(1.0, 0.0, 0.0),
+        ],
+        ..Default::default()
+    };
+
+    // Rendered at the pixel-art resolution so filtering keeps the one pixel lines
+    let mut renderer = Renderer::new(&Renderer::DEFAULT_PIXEL_SIZE, device, queue, &input);
+    renderer.set_outline(Outline {
+        technique: OutlineTechnique::InvertedHull,
+        edges: OutlineEdges::Outer,
+        color: LineColor::Fixed([0, 0, 255]),
+        thickness: 1,
+        ..Default::default()
+    });
+    let image = renderer.render_to_image();
+
+    // Line pixels may only border the background
+    let (width, height) = image.dimensions();
+    let transparent = |x: i64, y: i64| {
+        x < 0
+            || y < 0
+            || x >= width as i64
+            || y >= height as i64
+            || image.get_pixel(x as u32, y as u32)[3] == 0
+    };
+    let mut lines = 0;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a == 0 || b <= r.max(g) {
+            continue;
+        }
+        lines += 1;
+        let (x, y) = (x as i64, y as i64);
+        let borders_background = (-2..=2).any(|dy| (-2..=2).any(|dx| transparent(x + dx, y + dy)));
+        assert!(
+            borders_background,
+            "line pixel at {}, {} inside the model",
+            x, y
+        );
+    }
+    assert!(lines > 0, "no outline drawn");
+    // Both cubes are visible
+    assert!(image.pixels().any(|p| p[1] > p[0].max(p[2])));
+    assert!(image.pixels().any(|p| p[0] > p[1].max(p[2])));
+}
+
+#[test]
+fn mirroring_pose_flips_winding() {
+    let Ok((device, queue)) = headless::request_device(true) else {
+        return;
+    };
+    let input = model::Model {
+        bbox: model::BoundingBox::new(Vec3::splat(-1.0), Vec3::splat(1.0)),
+        nodes: vec![model::Node::default()],
+        meshes: vec![cube(Vec3::ZERO, 0.5, 0, 0)],
+        materials: vec![model::Material::default()],
+        ..Default::default()
+    };
+    let mut renderer = Renderer::new(&Renderer::DEFAULT_PIXEL_SIZE, device, queue, &input);
+    renderer.set_outline(Outline {
+        technique: OutlineTechnique::InvertedHull,
+        ..Default::default()
+    });
+    let rest = renderer.render_to_image();
+
+    // Mirrored along x the cube covers the same surface, wound the other way round
+    let mut pose = input.rest_pose();
+    pose.transforms[0].scale.x = -1.0;
+    renderer.set_pose(&pose);
+    assert_eq!(renderer.render_to_image(), rest);
+}
+
+#[test]
+fn palette_of_blended_render_is_not_darkened() {
+    let Ok((device, queue)) = headless::request_device(true) else {
+        return;
+    };
+    let extract = |alpha_mode, alpha| {
+        let input = model::Model {
+            bbox: model::BoundingBox::new(Vec3::splat(-1.0), Vec3::splat(1.0)),
+            nodes: vec![model::Node::default()],
+            meshes: vec![cube(Vec3::ZERO, 0.5, 0, 0)],
+            materials: vec![model::Material {
+                base_color: Vec4::new(1.0, 0.5, 0.0, alpha),
+                metallic: 0.0,
+                alpha_mode,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let renderer = Renderer::new(
+            &Renderer::DEFAULT_PIXEL_SIZE,
+            device.clone(),
+            queue.clone(),
+            &input,
+        );
+        renderer.render();
+        renderer.extract_palette(palette::extract::Method::MedianCut, 1, &[])
+    };
+
+    let opaque = extract(model::AlphaMode::Opaque, 1.0);
+    let blended = extract(model::AlphaMode::Blend, 0.5);
+    for (o, b) in opaque.colors[0].iter().zip(blended.colors[0]) {
+        assert!(
+            o.abs_diff(b) <= 2,
+            "{:?} != {:?}",
+            opaque.colors,
+            blended.colors
+        );
+    }
+}
@@ -0,0 +1,76 @@
+/// Renders of the model turned to `directions` equally spaced facings about its vertical axis,
+/// for games that need a sprite per facing direction
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Turntable {
+    /// Number of facings, usually 4, 8 or 16
+    pub directions: u32,
+    /// Model yaw in degrees of the first (N) facing.  Further facings step clockwise as seen
+    /// from above.
+    pub start_angle: f32,
+}
+
+impl Default for Turntable {
+    fn default() -> Self {
+        Self {
+            directions: 8,
+            start_angle: Self::DEFAULT_START_ANGLE,
+        }
+    }
+}
+
+impl Turntable {
+    /// GLTF models face +Z, towards the default camera.  Turning them half way round makes N
+    /// face away from the camera and E face screen right.
+    pub const DEFAULT_START_ANGLE: f32 = 180.0;
+
+    const COMPASS: [&'static str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+        "NW", "NNW",
+    ];
+
+    pub fn new(directions: u32, start_angle: f32) -> Self {
+        Self {
+            directions,
+            start_angle,
+        }
+    }
+
+    /// Clockwise angle in degrees of facing `index` from N
+    pub fn bearing(&self, index: u32) -> f32 {
+        index as f32 * 360.0 / self.directions.max(1) as f32
+    }
+
+    /// Model yaw in degrees for facing `index`
+    pub fn yaw(&self, index: u32) -> f32 {
+        // Positive yaw turns the model counter-clockwise seen from above
+        self.start_angle - self.bearing(index)
+    }
+
+    /// Compass label of facing `index`: N, E, S, W for 4 directions, N, NE, E... for 8 and
+    /// N, NNE, NE... for 16.  Other direction counts are labelled with the bearing in degrees.
+    pub fn label(&self, index: u32) -> String {
+        let directions = self.directions.max(1);
+        if Self::COMPASS.len() as u32 % directions == 0 {
+            let step = Self::COMPASS.len() as u32 / directions;
+            Self::COMPASS[(index % directions * step) as usize].to_string()
+        } else {
+            self.bearing(index).to_string()
+        }
+    }
+
+    /// Label and model yaw of each facing, starting from N
+    pub fn facings(&self) -> impl Iterator<Item = (String, f32)> + '_ {
+        (0..self.directions.max(1)).map(|index| (self.label(index), self.yaw(index)))
+    }
+}
+
+/// One facing of a turntable render
+pub struct TurntableFrame {
+    /// Compass label, see `Turntable::label`
+    pub direction: String,
+    /// Model yaw in degrees
+    pub yaw: f32,
+    pub image: image::RgbaImage,
+    /// Companion maps, in the order they were requested
+    pub maps: Vec<image::RgbaImage>,
+}
@@ -0,0 +1,322 @@
+use crate::{FrameInfo, Sheet, Size};
+
+/// Name of the animation a frame belongs to in engines without separate direction support
+fn clip_name(frame: &FrameInfo) -> String {
+    let name = [frame.animation.as_str(), frame.direction.as_str()]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join("_");
+    if name.is_empty() {
+        "default".to_string()
+    } else {
+        name
+    }
+}
+
+/// Runs of consecutive frames sharing a clip, as (clip, first index, last index)
+fn clip_ranges(frames: &[FrameInfo]) -> Vec<(String, usize, usize)> {
+    let mut ranges: Vec<(String, usize, usize)> = Vec::new();
+    for (idx, frame) in frames.iter().enumerate() {
+        let clip = clip_name(frame);
+        match ranges.last_mut() {
+            Some((name, _, last)) if *name == clip => *last = idx,
+            _ => ranges.push((clip, idx, idx)),
+        }
+    }
+    ranges
+}
+
+fn sheet_size(sheet: &Sheet) -> Size {
+    Size {
+        w: sheet.image.width(),
+        h: sheet.image.height(),
+    }
+}
+
+/// Frame pivot in pixels relative to the top-left of the trimmed rect
+fn trimmed_pivot(frame: &FrameInfo) -> (f32, f32) {
+    (
+        frame.pivot.x * frame.source_size.w as f32 - frame.source_offset.x as f32,
+        frame.pivot.y * frame.source_size.h as f32 - frame.source_offset.y as f32,
+    )
+}
+
+#[derive(serde::Serialize)]
+struct Metadata<'a> {
+    image: &'a str,
+    size: Size,
+    frames: &'a [FrameInfo],
+}
+
+/// Native format: every `FrameInfo` field as is
+pub fn write_json(sheet: &Sheet, image_name: &str) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(&Metadata {
+        image: image_name,
+        size: sheet_size(sheet),
+        frames: &sheet.frames,
+    })
+}
+
+/// Aseprite `--format json-array` sheet data, with a frame tag per clip
+pub fn write_aseprite(sheet: &Sheet, image_name: &str) -> Result<String, serde_json::Error> {
+    let frames: Vec<serde_json::Value> = sheet
+        .frames
+        .iter()
+        .map(|f| {
+            serde_json::json!({
+                "filename": f.name,
+                "frame": f.rect,
+                "rotated": false,
+                "trimmed": f.trimmed,
+                "spriteSourceSize": f.source_offset,
+                "sourceSize": f.source_size,
+                "duration": f.duration_ms,
+            })
+        })
+        .collect();
+    let tags: Vec<serde_json::Value> = clip_ranges(&sheet.frames)
+        .into_iter()
+        .map(|(name, from, to)| {
+            serde_json::json!({
+                "name": name,
+                "from": from,
+                "to": to,
+                "direction": "forward",
+            })
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&serde_json::json!({
+        "frames": frames,
+        "meta": {
+            "app": "pixit",
+            "version": env!("CARGO_PKG_VERSION"),
+            "image": image_name,
+            "format": "RGBA8888",
+            "size": sheet_size(sheet),
+            "scale": "1",
+            "frameTags": tags,
+            "layers": [],
+            "slices": [],
+        },
+    }))
+}
+
+/// TexturePacker generic JSON (hash) data
+pub fn write_texture_packer_json(
+    sheet: &Sheet,
+    image_name: &str,
+) -> Result<String, serde_json::Error> {
+    let frames: serde_json::Map<String, serde_json::Value> = sheet
+        .frames
+        .iter()
+        .map(|f| {
+            let value = serde_json::json!({
+                "frame": f.rect,
+                "rotated": false,
+                "trimmed": f.trimmed,
+                "spriteSourceSize": f.source_offset,
+                "sourceSize": f.source_size,
+                "pivot": f.pivot,
+            });
+            (f.name.clone(), value)
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&serde_json::json!({
+        "frames": frames,
+        "meta": {
+            "app": "pixit",
+            "version": env!("CARGO_PKG_VERSION"),
+            "image": image_name,
+            "format": "RGBA8888",
+            "size": sheet_size(sheet),
+            "scale": "1",
+        },
+    }))
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// TexturePacker generic XML data.  Trim offsets are only written for trimmed frames.
+pub fn write_texture_packer_xml(sheet: &Sheet, image_name: &str) -> String {
+    let size = sheet_size(sheet);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<TextureAtlas imagePath=\"{}\" width=\"{}\" height=\"{}\">\n",
+        escape_xml(image_name),
+        size.w,
+        size.h
+    ));
+    for f in &sheet.frames {
+        xml.push_str(&format!(
+            "    <sprite n=\"{}\" x=\"{}\" y=\"{}\" w=\"{}\" h=\"{}\" pX=\"{}\" pY=\"{}\"",
+            escape_xml(&f.name),
+            f.rect.x,
+            f.rect.y,
+            f.rect.w,
+            f.rect.h,
+            f.pivot.x,
+            f.pivot.y
+        ));
+        if f.trimmed {
+            xml.push_str(&format!(
+                " oX=\"{}\" oY=\"{}\" oW=\"{}\" oH=\"{}\"",
+                f.source_offset.x, f.source_offset.y, f.source_size.w, f.source_size.h
+            ));
+        }
+        xml.push_str("/>\n");
+    }
+    xml.push_str("</TextureAtlas>\n");
+    xml
+}
+
+/// Godot 4 SpriteFrames resource with one animation per clip.  The atlas is referenced as
+/// `res://<image_name>`, so the resource should be saved next to the image in the project.
+pub fn write_godot(sheet: &Sheet, image_name: &str) -> String {
+    let mut tres = format!(
+        "[gd_resource type=\"SpriteFrames\" load_steps={} format=3]\n\n",
+        sheet.frames.len() + 2
+    );
+    tres.push_str(&format!(
+        "[ext_resource type=\"Texture2D\" path=\"res://{}\" id=\"1\"]\n\n",
+        image_name
+    ));
+
+    // Trimmed frames are restored to their source size with the atlas margin
+    for (idx, f) in sheet.frames.iter().enumerate() {
+        tres.push_str(&format!(
+            "[sub_resource type=\"AtlasTexture\" id=\"AtlasTexture_{}\"]\n",
+            idx
+        ));
+        tres.push_str("atlas = ExtResource(\"1\")\n");
+        tres.push_str(&format!(
+            "region = Rect2({}, {}, {}, {})\n",
+            f.rect.x, f.rect.y, f.rect.w, f.rect.h
+        ));
+        if f.trimmed {
+            tres.push_str(&format!(
+                "margin = Rect2({}, {}, {}, {})\n",
+                f.source_offset.x,
+                f.source_offset.y,
+                f.source_size.w - f.rect.w,
+                f.source_size.h - f.rect.h
+            ));
+        }
+        tres.push('\n');
+    }
+
+    // Godot plays a clip at a fixed speed with per-frame duration multipliers
+    let animations: Vec<String> = clip_ranges(&sheet.frames)
+        .into_iter()
+        .map(|(name, from, to)| {
+            let frames = &sheet.frames[from..=to];
+            let base_ms = frames[0].duration_ms.max(1);
+            let frame_entries: Vec<String> = (from..=to)
+                .zip(frames)
+                .map(|(idx, f)| {
+                    format!(
+                        "{{\n\"duration\": {:?},\n\"texture\": SubResource(\"AtlasTexture_{}\")\n}}",
+                        f.duration_ms as f64 / base_ms as f64,
+                        idx
+                    )
+                })
+                .collect();
+            format!(
+                "{{\n\"frames\": [{}],\n\"loop\": true,\n\"name\": &\"{}\",\n\"speed\": {:?}\n}}",
+                frame_entries.join(", "),
+                name,
+                1000.0 / base_ms as f64
+            )
+        })
+        .collect();
+    tres.push_str(&format!(
+        "[resource]\nanimations = [{}]\n",
+        animations.join(", ")
+    ));
+    tres
+}
+
+/// Deterministic 128-bit hex id (FNV-1a) standing in for a Unity asset GUID
+fn unity_guid(name: &str) -> String {
+    let hash = |seed: u64| {
+        name.bytes().fold(seed, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    };
+    format!(
+        "{:016x}{:016x}",
+        hash(0xcbf2_9ce4_8422_2325),
+        hash(0x8422_2325_cbf2_9ce4)
+    )
+}
+
+/// Unity texture importer `.meta` slicing the atlas into a multiple-mode sprite, point
+/// filtered and uncompressed for pixel art.  Unity rects and pivots are measured from the
+/// bottom-left.
+pub fn write_unity_meta(sheet: &Sheet, image_name: &str) -> String {
+    let size = sheet_size(sheet);
+    let mut meta = format!(
+        "fileFormatVersion: 2\n\
+         guid: {}\n\
+         TextureImporter:\n\
+         \x20 serializedVersion: 12\n\
+         \x20 mipmaps:\n\
+         \x20   enableMipMap: 0\n\
+         \x20 textureSettings:\n\
+         \x20   serializedVersion: 2\n\
+         \x20   filterMode: 0\n\
+         \x20   wrapU: 1\n\
+         \x20   wrapV: 1\n\
+         \x20 textureType: 8\n\
+         \x20 textureShape: 1\n\
+         \x20 spriteMode: 2\n\
+         \x20 spritePixelsToUnits: {}\n\
+         \x20 alphaIsTransparency: 1\n\
+         \x20 textureCompression: 0\n\
+         \x20 spriteSheet:\n\
+         \x20   serializedVersion: 2\n\
+         \x20   sprites:\n",
+        unity_guid(image_name),
+        sheet.frames.first().map_or(100, |f| f.source_size.h)
+    );
+    for f in &sheet.frames {
+        let (px, py) = trimmed_pivot(f);
+        meta.push_str(&format!(
+            "    - serializedVersion: 2\n\
+             \x20     name: {}\n\
+             \x20     rect:\n\
+             \x20       serializedVersion: 2\n\
+             \x20       x: {}\n\
+             \x20       y: {}\n\
+             \x20       width: {}\n\
+             \x20       height: {}\n\
+             \x20     alignment: 9\n\
+             \x20     pivot: {{x: {}, y: {}}}\n\
+             \x20     border: {{x: 0, y: 0, z: 0, w: 0}}\n",
+            f.name,
+            f.rect.x,
+            size.h - f.rect.y - f.rect.h,
+            f.rect.w,
+            f.rect.h,
+            px / f.rect.w as f32,
+            1.0 - py / f.rect.h as f32
+        ));
+    }
+    meta
+}
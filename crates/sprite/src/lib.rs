@@ -0,0 +1,430 @@
+pub mod export;
+mod pack;
+#[cfg(test)]
+mod tests;
+
+use std::path::{Path, PathBuf};
+
+/// Errors produced while writing sprite sheets
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Json(serde_json::Error),
+    /// Nothing to pack
+    NoFrames,
+    /// Companion images don't match the packed frames one to one
+    FrameCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Image(e) => write!(f, "{}", e),
+            Error::Json(e) => write!(f, "{}", e),
+            Error::NoFrames => write!(f, "sprite sheet has no frames"),
+            Error::FrameCountMismatch { expected, found } => write!(
+                f,
+                "sprite sheet has {} frames but {} companion images were given",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+/// A single rendered sprite
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub image: image::RgbaImage,
+    /// Animation clip name, empty for a still
+    pub animation: String,
+    /// View direction label, eg "N" or "90"
+    pub direction: String,
+    /// Frame number within the animation
+    pub index: u32,
+    pub duration_ms: u32,
+}
+
+impl Frame {
+    /// Unique name built from the non-empty parts of animation, direction and index
+    pub fn name(&self) -> String {
+        let index = self.index.to_string();
+        [
+            self.animation.as_str(),
+            self.direction.as_str(),
+            index.as_str(),
+        ]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join("_")
+    }
+}
+
+/// How frames are laid out in the sheet
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Packing {
+    /// One row per (animation, direction), one column per frame, all cells the size of the
+    /// largest frame
+    #[default]
+    Grid,
+    /// Tight packing with the MaxRects bottom-left heuristic
+    MaxRects,
+}
+
+impl Packing {
+    pub const ALL: [Packing; 2] = [Packing::Grid, Packing::MaxRects];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Packing::Grid => "grid",
+            Packing::MaxRects => "maxrects",
+        }
+    }
+}
+
+impl std::str::FromStr for Packing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Packing::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown packing '{}', expected grid or maxrects", s))
+    }
+}
+
+/// Sheet metadata file format
+#[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Format {
+    /// Native JSON with every frame field
+    #[default]
+    Json,
+    /// Aseprite JSON array with frame tags per animation
+    Aseprite,
+    /// Godot 4 SpriteFrames resource
+    Godot,
+    /// TexturePacker generic JSON hash
+    TexturePackerJson,
+    /// TexturePacker generic XML
+    TexturePackerXml,
+    /// Unity texture importer `.meta` with sprite slices
+    Unity,
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Format::Json,
+        Format::Aseprite,
+        Format::Godot,
+        Format::TexturePackerJson,
+        Format::TexturePackerXml,
+        Format::Unity,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Aseprite => "aseprite",
+            Format::Godot => "godot",
+            Format::TexturePackerJson => "texturepacker-json",
+            Format::TexturePackerXml => "texturepacker-xml",
+            Format::Unity => "unity",
+        }
+    }
+
+    /// Where the metadata for the atlas at `image_path` is written.  Formats sharing an
+    /// extension get distinct suffixes so several can be exported side by side.
+    pub fn metadata_path(&self, image_path: &Path) -> PathBuf {
+        let stem = image_path.file_stem().unwrap_or_default().to_string_lossy();
+        let file_name = match self {
+            Format::Json => format!("{}.json", stem),
+            Format::Aseprite => format!("{}.aseprite.json", stem),
+            Format::Godot => format!("{}.tres", stem),
+            Format::TexturePackerJson => format!("{}.tp.json", stem),
+            Format::TexturePackerXml => format!("{}.xml", stem),
+            // Unity picks up importer settings from `<asset>.meta`
+            Format::Unity => format!(
+                "{}.meta",
+                image_path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+        };
+        image_path.with_file_name(file_name)
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Format::ALL.iter().map(|f| f.name()).collect();
+                format!(
+                    "unknown format '{}', expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SheetOptions {
+    pub packing: Packing,
+    /// Transparent pixels between frames and around the sheet border
+    pub padding: u32,
+    /// Crop fully transparent borders from each frame
+    pub trim: bool,
+    /// Sheet width for `Packing::MaxRects`.  Picked from the total frame area when `None`.
+    pub max_width: Option<u32>,
+    /// Frame pivot relative to the untrimmed frame size, (0,0) top-left to (1,1) bottom-right
+    pub pivot: [f32; 2],
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self {
+            packing: Packing::Grid,
+            padding: 1,
+            trim: false,
+            max_width: None,
+            // Bottom center, where a character touches the ground
+            pivot: [0.5, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Size {
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Placement of one frame in the sheet
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FrameInfo {
+    pub name: String,
+    pub animation: String,
+    pub direction: String,
+    pub index: u32,
+    /// Location of the (possibly trimmed) frame in the sheet
+    pub rect: Rect,
+    pub trimmed: bool,
+    /// Size of the frame before trimming
+    pub source_size: Size,
+    /// Offset of `rect` within the untrimmed frame
+    pub source_offset: Rect,
+    /// Normalized pivot relative to `source_size`
+    pub pivot: Point,
+    pub duration_ms: u32,
+}
+
+/// Packed sprite atlas with per-frame metadata
+#[derive(Debug, Clone)]
+pub struct Sheet {
+    pub image: image::RgbaImage,
+    /// Frames in input order
+    pub frames: Vec<FrameInfo>,
+}
+
+impl Sheet {
+    pub fn pack(frames: &[Frame], options: &SheetOptions) -> Result<Self, Error> {
+        if frames.is_empty() {
+            return Err(Error::NoFrames);
+        }
+
+        let crops: Vec<Rect> = frames
+            .iter()
+            .map(|f| {
+                if options.trim {
+                    opaque_bounds(&f.image)
+                } else {
+                    Rect {
+                        x: 0,
+                        y: 0,
+                        w: f.image.width(),
+                        h: f.image.height(),
+                    }
+                }
+            })
+            .collect();
+        let sizes: Vec<Size> = crops.iter().map(|r| Size { w: r.w, h: r.h }).collect();
+
+        let (sheet_size, positions) = match options.packing {
+            Packing::Grid => {
+                let groups: Vec<(&str, &str)> = frames
+                    .iter()
+                    .map(|f| (f.animation.as_str(), f.direction.as_str()))
+                    .collect();
+                pack::grid(&sizes, &groups, options.padding)
+            }
+            Packing::MaxRects => pack::max_rects(&sizes, options.padding, options.max_width),
+        };
+
+        let mut image = image::RgbaImage::new(sheet_size.w, sheet_size.h);
+        let mut infos = Vec::with_capacity(frames.len());
+        for ((frame, crop), (x, y)) in frames.iter().zip(&crops).zip(positions) {
+            let view = image::imageops::crop_imm(&frame.image, crop.x, crop.y, crop.w, crop.h);
+            image::imageops::replace(&mut image, &*view, x as i64, y as i64);
+
+            infos.push(FrameInfo {
+                name: frame.name(),
+                animation: frame.animation.clone(),
+                direction: frame.direction.clone(),
+                index: frame.index,
+                rect: Rect {
+                    x,
+                    y,
+                    w: crop.w,
+                    h: crop.h,
+                },
+                trimmed: crop.w != frame.image.width() || crop.h != frame.image.height(),
+                source_size: Size {
+                    w: frame.image.width(),
+                    h: frame.image.height(),
+                },
+                source_offset: *crop,
+                pivot: Point {
+                    x: options.pivot[0],
+                    y: options.pivot[1],
+                },
+                duration_ms: frame.duration_ms,
+            });
+        }
+
+        Ok(Self {
+            image,
+            frames: infos,
+        })
+    }
+
+    /// Atlas of per-frame companion images (eg normal maps) laid out exactly like the color
+    /// frames, so the frame metadata applies to both.  `images` parallel the frames passed to
+    /// `pack` and must have the same sizes.
+    pub fn companion(&self, images: &[image::RgbaImage]) -> Result<image::RgbaImage, Error> {
+        if images.len() != self.frames.len() {
+            return Err(Error::FrameCountMismatch {
+                expected: self.frames.len(),
+                found: images.len(),
+            });
+        }
+
+        let mut atlas = image::RgbaImage::new(self.image.width(), self.image.height());
+        for (info, image) in self.frames.iter().zip(images) {
+            let crop = &info.source_offset;
+            let view = image::imageops::crop_imm(image, crop.x, crop.y, crop.w, crop.h);
+            image::imageops::replace(&mut atlas, &*view, info.rect.x as i64, info.rect.y as i64);
+        }
+        Ok(atlas)
+    }
+
+    /// Where the companion atlas `name` (eg "normal") of the atlas at `image_path` is written,
+    /// `dir/sheet.png` -> `dir/sheet_normal.png`
+    pub fn companion_path(image_path: &Path, name: &str) -> PathBuf {
+        let stem = image_path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = image_path
+            .extension()
+            .map_or("png".into(), |e| e.to_string_lossy());
+        image_path.with_file_name(format!("{}_{}.{}", stem, name, ext))
+    }
+
+    /// Frame metadata in `format`.  `image_name` is recorded as the atlas file name.
+    pub fn metadata(&self, format: Format, image_name: &str) -> Result<String, Error> {
+        Ok(match format {
+            Format::Json => export::write_json(self, image_name)?,
+            Format::Aseprite => export::write_aseprite(self, image_name)?,
+            Format::Godot => export::write_godot(self, image_name),
+            Format::TexturePackerJson => export::write_texture_packer_json(self, image_name)?,
+            Format::TexturePackerXml => export::write_texture_packer_xml(self, image_name),
+            Format::Unity => export::write_unity_meta(self, image_name),
+        })
+    }
+
+    /// Write the atlas to `path` (PNG) and its metadata next to it in each of `formats`
+    pub fn save(&self, path: impl AsRef<Path>, formats: &[Format]) -> Result<(), Error> {
+        let path = path.as_ref();
+        self.image.save(path)?;
+
+        let image_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        for format in formats {
+            std::fs::write(
+                format.metadata_path(path),
+                self.metadata(*format, &image_name)?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Bounding rect of the pixels with non-zero alpha.  Fully transparent images keep a single
+/// pixel so every frame has a valid rect.
+fn opaque_bounds(image: &image::RgbaImage) -> Rect {
+    let mut min = (u32::MAX, u32::MAX);
+    let mut max = (0, 0);
+    for (x, y, p) in image.enumerate_pixels() {
+        if p[3] != 0 {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+    }
+
+    if min.0 > max.0 {
+        return Rect {
+            x: 0,
+            y: 0,
+            w: 1,
+            h: 1,
+        };
+    }
+    Rect {
+        x: min.0,
+        y: min.1,
+        w: max.0 - min.0 + 1,
+        h: max.1 - min.1 + 1,
+    }
+}
@@ -0,0 +1,188 @@
+use crate::Size;
+
+/// Free or used area of the sheet while packing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Area {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+impl Area {
+    fn right(&self) -> u32 {
+        self.x + self.w
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.h
+    }
+
+    fn intersects(&self, other: &Area) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    fn contains(&self, other: &Area) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+}
+
+/// Lay frames out in rows of equally sized cells.  Each distinct `group` gets a row, in order
+/// of first appearance, and its frames fill the row left to right.  Returns the sheet size and
+/// each frame's top-left corner.
+pub fn grid(sizes: &[Size], groups: &[(&str, &str)], padding: u32) -> (Size, Vec<(u32, u32)>) {
+    let cell = Size {
+        w: sizes.iter().map(|s| s.w).max().unwrap_or(0),
+        h: sizes.iter().map(|s| s.h).max().unwrap_or(0),
+    };
+
+    let mut rows: Vec<(&(&str, &str), u32)> = Vec::new();
+    let mut positions = Vec::with_capacity(sizes.len());
+    for group in groups {
+        let row = match rows.iter().position(|(g, _)| *g == group) {
+            Some(row) => row,
+            None => {
+                rows.push((group, 0));
+                rows.len() - 1
+            }
+        };
+        let column = rows[row].1;
+        rows[row].1 += 1;
+        positions.push((
+            padding + column * (cell.w + padding),
+            padding + row as u32 * (cell.h + padding),
+        ));
+    }
+
+    let columns = rows.iter().map(|(_, n)| *n).max().unwrap_or(0);
+    let size = Size {
+        w: padding + columns * (cell.w + padding),
+        h: padding + rows.len() as u32 * (cell.h + padding),
+    };
+    (size, positions)
+}
+
+/// Pack frames into a sheet of fixed width and minimal height using MaxRects with the
+/// bottom-left rule: each frame goes where its bottom edge is highest, then leftmost.  Frames
+/// are placed tallest first.  Returns the sheet size and each frame's top-left corner in input
+/// order.
+pub fn max_rects(sizes: &[Size], padding: u32, max_width: Option<u32>) -> (Size, Vec<(u32, u32)>) {
+    // Each frame reserves `padding` pixels to its right and below, the sheet adds the same to
+    // the top and left border
+    let padded: Vec<Size> = sizes
+        .iter()
+        .map(|s| Size {
+            w: s.w + padding,
+            h: s.h + padding,
+        })
+        .collect();
+
+    let widest = padded.iter().map(|s| s.w).max().unwrap_or(0);
+    let width = match max_width {
+        Some(w) => w.saturating_sub(padding).max(widest),
+        None => {
+            let area: u64 = padded.iter().map(|s| s.w as u64 * s.h as u64).sum();
+            ((area as f64).sqrt().ceil() as u32).max(widest)
+        }
+    };
+
+    let mut free = vec![Area {
+        x: 0,
+        y: 0,
+        w: width,
+        h: u32::MAX / 2,
+    }];
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| {
+        (
+            std::cmp::Reverse(padded[i].h),
+            std::cmp::Reverse(padded[i].w),
+        )
+    });
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let mut height = 0;
+    for i in order {
+        let size = padded[i];
+        let placed = free
+            .iter()
+            .filter(|f| f.w >= size.w && f.h >= size.h)
+            .min_by_key(|f| (f.y + size.h, f.x))
+            .map(|f| Area {
+                x: f.x,
+                y: f.y,
+                w: size.w,
+                h: size.h,
+            })
+            .expect("Free space is unbounded in height");
+
+        split_free(&mut free, &placed);
+        positions[i] = (placed.x + padding, placed.y + padding);
+        height = height.max(placed.bottom());
+    }
+
+    let size = Size {
+        w: width + padding,
+        h: height + padding,
+    };
+    (size, positions)
+}
+
+/// Remove `used` from the free areas, keeping the maximal free rectangles around it
+fn split_free(free: &mut Vec<Area>, used: &Area) {
+    let mut split = Vec::new();
+    free.retain(|f| {
+        if !f.intersects(used) {
+            return true;
+        }
+        if used.x > f.x {
+            split.push(Area {
+                w: used.x - f.x,
+                ..*f
+            });
+        }
+        if used.right() < f.right() {
+            split.push(Area {
+                x: used.right(),
+                w: f.right() - used.right(),
+                ..*f
+            });
+        }
+        if used.y > f.y {
+            split.push(Area {
+                h: used.y - f.y,
+                ..*f
+            });
+        }
+        if used.bottom() < f.bottom() {
+            split.push(Area {
+                y: used.bottom(),
+                h: f.bottom() - used.bottom(),
+                ..*f
+            });
+        }
+        false
+    });
+    free.extend(split);
+
+    // Drop areas fully covered by another one
+    let mut i = 0;
+    while i < free.len() {
+        let covered = free
+            .iter()
+            .enumerate()
+            .any(|(j, other)| j != i && other.contains(&free[i]) && (other != &free[i] || j < i));
+        if covered {
+            free.swap_remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
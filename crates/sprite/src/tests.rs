@@ -0,0 +1,392 @@
+use crate::{pack, Format, Frame, FrameInfo, Packing, Point, Rect, Sheet, SheetOptions, Size};
+
+/// Assorted frame sizes from a fixed LCG so failures reproduce
+fn sizes(count: usize) -> Vec<Size> {
+    let mut state = 12345u32;
+    let mut next = move |range: u32| {
+        state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        1 + (state >> 16) % range
+    };
+    (0..count)
+        .map(|_| Size {
+            w: next(40),
+            h: next(40),
+        })
+        .collect()
+}
+
+fn rects(sizes: &[Size], positions: &[(u32, u32)]) -> Vec<Rect> {
+    sizes
+        .iter()
+        .zip(positions)
+        .map(|(s, &(x, y))| Rect {
+            x,
+            y,
+            w: s.w,
+            h: s.h,
+        })
+        .collect()
+}
+
+/// Every rect lies inside the sheet with at least `padding` pixels to the border and to every
+/// other rect
+fn assert_padded(sheet: Size, rects: &[Rect], padding: u32) {
+    for (i, a) in rects.iter().enumerate() {
+        assert!(
+            a.x >= padding && a.y >= padding,
+            "{:?} too close to the top left",
+            a
+        );
+        assert!(
+            a.x + a.w + padding <= sheet.w && a.y + a.h + padding <= sheet.h,
+            "{:?} outside {:?}",
+            a,
+            sheet
+        );
+        for b in &rects[i + 1..] {
+            let apart = a.x + a.w + padding <= b.x
+                || b.x + b.w + padding <= a.x
+                || a.y + a.h + padding <= b.y
+                || b.y + b.h + padding <= a.y;
+            assert!(apart, "{:?} and {:?} closer than {}", a, b, padding);
+        }
+    }
+}
+
+#[test]
+fn max_rects_does_not_overlap() {
+    for padding in [0, 1, 3] {
+        let sizes = sizes(50);
+        let (sheet, positions) = pack::max_rects(&sizes, padding, None);
+        assert_padded(sheet, &rects(&sizes, &positions), padding);
+    }
+}
+
+#[test]
+fn max_rects_honours_max_width() {
+    let sizes = sizes(50);
+    for max_width in [64, 100, 256] {
+        let (sheet, positions) = pack::max_rects(&sizes, 2, Some(max_width));
+        assert_eq!(sheet.w, max_width);
+        assert_padded(sheet, &rects(&sizes, &positions), 2);
+    }
+
+    // A frame wider than the limit widens the sheet rather than overflowing it
+    let wide = [Size { w: 80, h: 4 }, Size { w: 10, h: 10 }];
+    let (sheet, positions) = pack::max_rects(&wide, 1, Some(32));
+    assert_eq!(sheet.w, 82);
+    assert_padded(sheet, &rects(&wide, &positions), 1);
+}
+
+#[test]
+fn max_rects_fills_rows() {
+    // Sixteen equal squares fit a 4x4 block exactly
+    let sizes = vec![Size { w: 8, h: 8 }; 16];
+    let (sheet, positions) = pack::max_rects(&sizes, 1, Some(1 + 4 * 9));
+    assert_eq!(sheet, Size { w: 37, h: 37 });
+    assert_padded(sheet, &rects(&sizes, &positions), 1);
+}
+
+#[test]
+fn grid_rows_per_group() {
+    let sizes = sizes(7);
+    let groups = [
+        ("walk", "N"),
+        ("walk", "N"),
+        ("walk", "S"),
+        ("walk", "N"),
+        ("idle", ""),
+        ("walk", "S"),
+        ("idle", ""),
+    ];
+    for padding in [0, 2] {
+        let (sheet, positions) = pack::grid(&sizes, &groups, padding);
+        assert_padded(sheet, &rects(&sizes, &positions), padding);
+
+        let cell_w = sizes.iter().map(|s| s.w).max().unwrap() + padding;
+        let cell_h = sizes.iter().map(|s| s.h).max().unwrap() + padding;
+        let cells: Vec<(u32, u32)> = positions
+            .iter()
+            .map(|&(x, y)| ((x - padding) / cell_w, (y - padding) / cell_h))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![(0, 0), (1, 0), (0, 1), (2, 0), (0, 2), (1, 1), (1, 2)]
+        );
+        assert_eq!(
+            sheet,
+            Size {
+                w: padding + 3 * cell_w,
+                h: padding + 3 * cell_h,
+            }
+        );
+    }
+}
+
+/// Frame of `w`x`h` with an opaque gradient block at `content`
+fn frame(index: u32, w: u32, h: u32, content: Rect) -> Frame {
+    let mut image = image::RgbaImage::new(w, h);
+    for y in content.y..content.y + content.h {
+        for x in content.x..content.x + content.w {
+            image.put_pixel(x, y, image::Rgba([x as u8, y as u8, index as u8, 255]));
+        }
+    }
+    Frame {
+        image,
+        animation: "walk".to_string(),
+        direction: String::new(),
+        index,
+        duration_ms: 100,
+    }
+}
+
+#[test]
+fn trimmed_frames_round_trip() {
+    // Frame size and opaque block x, y, w, h
+    let layouts = [
+        (16, 16, [3, 5, 6, 9]),
+        (16, 16, [0, 0, 16, 16]),
+        (20, 12, [19, 11, 1, 1]),
+        (8, 8, [2, 0, 4, 8]),
+    ];
+    let contents: Vec<Rect> = layouts
+        .iter()
+        .map(|&(_, _, [x, y, w, h])| Rect { x, y, w, h })
+        .collect();
+    let frames: Vec<Frame> = layouts
+        .iter()
+        .zip(&contents)
+        .enumerate()
+        .map(|(i, (&(w, h, _), &content))| frame(i as u32, w, h, content))
+        .collect();
+
+    for packing in Packing::ALL {
+        let options = SheetOptions {
+            packing,
+            padding: 1,
+            trim: true,
+            ..Default::default()
+        };
+        let sheet = Sheet::pack(&frames, &options).unwrap();
+
+        let placed: Vec<Rect> = sheet.frames.iter().map(|f| f.rect).collect();
+        let sheet_size = Size {
+            w: sheet.image.width(),
+            h: sheet.image.height(),
+        };
+        assert_padded(sheet_size, &placed, 1);
+
+        for ((frame, info), content) in frames.iter().zip(&sheet.frames).zip(&contents) {
+            let offset = info.source_offset;
+            assert_eq!(offset, *content, "{:?}", packing);
+            assert_eq!((info.rect.w, info.rect.h), (offset.w, offset.h));
+            assert_eq!(
+                info.source_size,
+                Size {
+                    w: frame.image.width(),
+                    h: frame.image.height(),
+                }
+            );
+            assert_eq!(
+                info.trimmed,
+                offset.w != frame.image.width() || offset.h != frame.image.height()
+            );
+
+            // Placing the sheet rect at the offset restores the source frame
+            for (x, y, p) in frame.image.enumerate_pixels() {
+                let inside = x >= offset.x
+                    && y >= offset.y
+                    && x < offset.x + offset.w
+                    && y < offset.y + offset.h;
+                if inside {
+                    let sx = info.rect.x + x - offset.x;
+                    let sy = info.rect.y + y - offset.y;
+                    assert_eq!(sheet.image.get_pixel(sx, sy), p, "{:?}", packing);
+                } else {
+                    assert_eq!(p[3], 0, "{:?} trimmed an opaque pixel", packing);
+                }
+            }
+        }
+    }
+}
+
+fn frame_info(
+    animation: &str,
+    direction: &str,
+    index: u32,
+    rect: Rect,
+    source_offset: Rect,
+    duration_ms: u32,
+) -> FrameInfo {
+    let name = [animation, direction, &index.to_string()]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join("_");
+    FrameInfo {
+        name,
+        animation: animation.to_string(),
+        direction: direction.to_string(),
+        index,
+        rect,
+        trimmed: rect.w != 16 || rect.h != 16,
+        source_size: Size { w: 16, h: 16 },
+        source_offset,
+        pivot: Point { x: 0.5, y: 1.0 },
+        duration_ms,
+    }
+}
+
+/// 40x20 sheet of 16x16 frames: two of "walk" facing N, the first trimmed, and a trimmed
+/// "idle" frame.  Trimmed pivots land on the bottom center of their rects.
+fn exported_sheet() -> Sheet {
+    let rect = |x, y, w, h| Rect { x, y, w, h };
+    Sheet {
+        image: image::RgbaImage::new(40, 20),
+        frames: vec![
+            frame_info("walk", "N", 0, rect(1, 1, 8, 10), rect(4, 6, 8, 10), 100),
+            frame_info("walk", "N", 1, rect(10, 1, 16, 16), rect(0, 0, 16, 16), 150),
+            frame_info("idle", "", 0, rect(27, 1, 4, 4), rect(6, 12, 4, 4), 200),
+        ],
+    }
+}
+
+fn metadata(format: Format) -> String {
+    exported_sheet().metadata(format, "sheet.png").unwrap()
+}
+
+fn json_rect(value: &serde_json::Value) -> [u64; 4] {
+    ["x", "y", "w", "h"].map(|k| value[k].as_u64().unwrap())
+}
+
+#[test]
+fn exports_json() {
+    let json: serde_json::Value = serde_json::from_str(&metadata(Format::Json)).unwrap();
+    assert_eq!(json["image"], "sheet.png");
+    assert_eq!(json_rect(&json["frames"][0]["rect"]), [1, 1, 8, 10]);
+    assert_eq!(
+        json_rect(&json["frames"][0]["source_offset"]),
+        [4, 6, 8, 10]
+    );
+    assert_eq!(json["frames"][0]["trimmed"], true);
+    assert_eq!(json["frames"][1]["trimmed"], false);
+    assert_eq!(json_rect(&json["frames"][2]["rect"]), [27, 1, 4, 4]);
+    let durations: Vec<u64> = (0..3)
+        .map(|i| json["frames"][i]["duration_ms"].as_u64().unwrap())
+        .collect();
+    assert_eq!(durations, [100, 150, 200]);
+}
+
+#[test]
+fn exports_aseprite() {
+    let json: serde_json::Value = serde_json::from_str(&metadata(Format::Aseprite)).unwrap();
+    let frame = &json["frames"][0];
+    assert_eq!(frame["filename"], "walk_N_0");
+    assert_eq!(json_rect(&frame["frame"]), [1, 1, 8, 10]);
+    assert_eq!(json_rect(&frame["spriteSourceSize"]), [4, 6, 8, 10]);
+    assert_eq!(frame["sourceSize"], serde_json::json!({ "w": 16, "h": 16 }));
+    assert_eq!(json_rect(&json["frames"][2]["frame"]), [27, 1, 4, 4]);
+    let durations: Vec<u64> = (0..3)
+        .map(|i| json["frames"][i]["duration"].as_u64().unwrap())
+        .collect();
+    assert_eq!(durations, [100, 150, 200]);
+
+    let tags = &json["meta"]["frameTags"];
+    assert_eq!(tags[0]["name"], "walk_N");
+    assert_eq!(
+        (tags[0]["from"].as_u64(), tags[0]["to"].as_u64()),
+        (Some(0), Some(1))
+    );
+    assert_eq!(tags[1]["name"], "idle");
+    assert_eq!(
+        (tags[1]["from"].as_u64(), tags[1]["to"].as_u64()),
+        (Some(2), Some(2))
+    );
+    assert_eq!(
+        json["meta"]["size"],
+        serde_json::json!({ "w": 40, "h": 20 })
+    );
+}
+
+#[test]
+fn exports_texture_packer_json() {
+    let json: serde_json::Value =
+        serde_json::from_str(&metadata(Format::TexturePackerJson)).unwrap();
+    let frame = &json["frames"]["walk_N_0"];
+    assert_eq!(json_rect(&frame["frame"]), [1, 1, 8, 10]);
+    assert_eq!(json_rect(&frame["spriteSourceSize"]), [4, 6, 8, 10]);
+    assert_eq!(frame["trimmed"], true);
+    assert_eq!(frame["pivot"], serde_json::json!({ "x": 0.5, "y": 1.0 }));
+    assert_eq!(
+        json_rect(&json["frames"]["walk_N_1"]["frame"]),
+        [10, 1, 16, 16]
+    );
+    assert_eq!(
+        json_rect(&json["frames"]["idle_0"]["spriteSourceSize"]),
+        [6, 12, 4, 4]
+    );
+    assert_eq!(json["meta"]["image"], "sheet.png");
+}
+
+#[test]
+fn exports_texture_packer_xml() {
+    let xml = metadata(Format::TexturePackerXml);
+    assert!(xml.contains(r#"<TextureAtlas imagePath="sheet.png" width="40" height="20">"#));
+    // Trim offsets only on trimmed frames
+    assert!(xml.contains(
+        r#"<sprite n="walk_N_0" x="1" y="1" w="8" h="10" pX="0.5" pY="1" oX="4" oY="6" oW="16" oH="16"/>"#
+    ));
+    assert!(xml.contains(r#"<sprite n="walk_N_1" x="10" y="1" w="16" h="16" pX="0.5" pY="1"/>"#));
+    assert!(xml.contains(
+        r#"<sprite n="idle_0" x="27" y="1" w="4" h="4" pX="0.5" pY="1" oX="6" oY="12" oW="16" oH="16"/>"#
+    ));
+}
+
+#[test]
+fn exports_godot() {
+    let tres = metadata(Format::Godot);
+    assert!(tres.contains(r#"[ext_resource type="Texture2D" path="res://sheet.png" id="1"]"#));
+    assert!(tres.contains(
+        "[sub_resource type=\"AtlasTexture\" id=\"AtlasTexture_0\"]\n\
+         atlas = ExtResource(\"1\")\n\
+         region = Rect2(1, 1, 8, 10)\n\
+         margin = Rect2(4, 6, 8, 6)\n"
+    ));
+    // Untrimmed frames have no margin
+    assert!(tres.contains("region = Rect2(10, 1, 16, 16)\n\n"));
+    assert!(tres.contains("margin = Rect2(6, 12, 12, 12)\n"));
+
+    // Durations relative to the first frame of each clip at a speed matching it
+    assert!(tres.contains("\"duration\": 1.0,\n\"texture\": SubResource(\"AtlasTexture_0\")"));
+    assert!(tres.contains("\"duration\": 1.5,\n\"texture\": SubResource(\"AtlasTexture_1\")"));
+    assert!(tres.contains("\"name\": &\"walk_N\",\n\"speed\": 10.0"));
+    assert!(tres.contains("\"name\": &\"idle\",\n\"speed\": 5.0"));
+}
+
+#[test]
+fn exports_unity() {
+    let meta = metadata(Format::Unity);
+    assert!(meta.contains("  spriteMode: 2\n"));
+    // Rects measured from the bottom of the 20 pixel high sheet, pivots relative to the rect
+    assert!(meta.contains(
+        "      name: walk_N_0\n\
+         \x20     rect:\n\
+         \x20       serializedVersion: 2\n\
+         \x20       x: 1\n\
+         \x20       y: 9\n\
+         \x20       width: 8\n\
+         \x20       height: 10\n\
+         \x20     alignment: 9\n\
+         \x20     pivot: {x: 0.5, y: 0}\n"
+    ));
+    assert!(meta.contains("name: walk_N_1\n"));
+    assert!(meta.contains(
+        "       x: 27\n\
+         \x20       y: 15\n\
+         \x20       width: 4\n\
+         \x20       height: 4\n\
+         \x20     alignment: 9\n\
+         \x20     pivot: {x: 0.5, y: 0}\n"
+    ));
+}